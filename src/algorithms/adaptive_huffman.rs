@@ -0,0 +1,188 @@
+use crate::bit_map::{BitMap, BitSource};
+use std::io::Error;
+
+// Adaptive Huffman coding (FGK algorithm). Encoder and decoder start with the tree of a single
+// NYT ("not yet transmitted") node and update their trees identically after every symbol, so no
+// dictionary is stored in archive. A symbol seen for the first time is sent as code of NYT node
+// followed by 8 raw bits of the symbol.
+
+const MAX_NODES: usize = 2 * 256 + 1; // 256 leaves, NYT and their parents
+
+#[derive(Clone, Copy)]
+struct TreeNode {
+    weight: u64,
+    order: usize, // nodes are numbered so that weights never decrease with the number
+    parent: Option<usize>,
+    children: Option<(usize, usize)>,
+    symbol: Option<u8>,
+}
+
+pub struct AdaptiveHuffman {
+    nodes: Vec<TreeNode>,
+    by_order: Vec<usize>, // order -> index in `nodes`
+    leaves: [Option<usize>; 256],
+    nyt: usize,
+    root: usize,
+}
+
+impl AdaptiveHuffman {
+    pub fn new() -> Self {
+        let root = TreeNode {
+            weight: 0,
+            order: MAX_NODES - 1,
+            parent: None,
+            children: None,
+            symbol: None,
+        };
+
+        Self {
+            nodes: vec![root],
+            by_order: vec![0; MAX_NODES],
+            leaves: [None; 256],
+            nyt: 0,
+            root: 0,
+        }
+    }
+
+    pub fn encode(&mut self, byte: u8, bits: &mut BitMap) {
+        match self.leaves[byte as usize] {
+            Some(leaf) => bits.add_bit_sequence(&self.code_of(leaf)),
+            None => {
+                bits.add_bit_sequence(&self.code_of(self.nyt));
+                bits.add_value(byte as u64, 8);
+            }
+        }
+
+        self.update(byte);
+    }
+
+    pub fn decode(&mut self, bits: &mut impl BitSource) -> Result<u8, Error> {
+        let mut node = self.root;
+        while let Some((left, right)) = self.nodes[node].children {
+            node = if bits.read_bit()? == 0 { left } else { right };
+        }
+
+        let byte = match self.nodes[node].symbol {
+            Some(byte) => byte,
+            None => bits.read_bits(8)? as u8, // reached NYT
+        };
+
+        self.update(byte);
+        Ok(byte)
+    }
+
+    /// path from root to node, 0 - left, 1 - right
+    fn code_of(&self, node: usize) -> Vec<u8> {
+        let mut code = vec![];
+
+        let mut current = node;
+        while let Some(parent) = self.nodes[current].parent {
+            let (left, _) = self.nodes[parent].children.unwrap();
+            code.push(if left == current { 0 } else { 1 });
+            current = parent;
+        }
+
+        code.reverse();
+        code
+    }
+
+    fn update(&mut self, byte: u8) {
+        let mut node = match self.leaves[byte as usize] {
+            Some(leaf) => leaf,
+            None => self.split_nyt(byte),
+        };
+
+        loop {
+            let leader = self.block_leader(node);
+            if leader != node && Some(leader) != self.nodes[node].parent {
+                self.swap(node, leader);
+            }
+
+            self.nodes[node].weight += 1;
+
+            match self.nodes[node].parent {
+                Some(parent) => node = parent,
+                None => break,
+            }
+        }
+    }
+
+    /// NYT node gives birth to new NYT (left) and leaf of the new symbol (right), returns the leaf
+    fn split_nyt(&mut self, byte: u8) -> usize {
+        let old_nyt = self.nyt;
+        let order = self.nodes[old_nyt].order;
+
+        let new_nyt = self.push_node(order - 2, old_nyt, None);
+        let leaf = self.push_node(order - 1, old_nyt, Some(byte));
+
+        self.nodes[old_nyt].children = Some((new_nyt, leaf));
+        self.leaves[byte as usize] = Some(leaf);
+        self.nyt = new_nyt;
+
+        leaf
+    }
+
+    fn push_node(&mut self, order: usize, parent: usize, symbol: Option<u8>) -> usize {
+        self.nodes.push(TreeNode {
+            weight: 0,
+            order,
+            parent: Some(parent),
+            children: None,
+            symbol,
+        });
+
+        let index = self.nodes.len() - 1;
+        self.by_order[order] = index;
+        index
+    }
+
+    /// node with the highest order among nodes of the same weight
+    fn block_leader(&self, node: usize) -> usize {
+        let weight = self.nodes[node].weight;
+
+        let mut order = self.nodes[node].order;
+        while order + 1 < MAX_NODES && self.nodes[self.by_order[order + 1]].weight == weight {
+            order += 1;
+        }
+
+        self.by_order[order]
+    }
+
+    /// exchanges positions of two subtrees, neither of them may contain the other
+    fn swap(&mut self, a: usize, b: usize) {
+        let parent_a = self.nodes[a].parent.unwrap();
+        let parent_b = self.nodes[b].parent.unwrap();
+
+        if parent_a == parent_b {
+            let (left, right) = self.nodes[parent_a].children.unwrap();
+            self.nodes[parent_a].children = Some((right, left));
+        } else {
+            self.replace_child(parent_a, a, b);
+            self.replace_child(parent_b, b, a);
+
+            self.nodes[a].parent = Some(parent_b);
+            self.nodes[b].parent = Some(parent_a);
+        }
+
+        let (order_a, order_b) = (self.nodes[a].order, self.nodes[b].order);
+        self.nodes[a].order = order_b;
+        self.nodes[b].order = order_a;
+        self.by_order[order_a] = b;
+        self.by_order[order_b] = a;
+    }
+
+    fn replace_child(&mut self, parent: usize, old: usize, new: usize) {
+        let (left, right) = self.nodes[parent].children.unwrap();
+        self.nodes[parent].children = Some(if left == old {
+            (new, right)
+        } else {
+            (left, new)
+        });
+    }
+}
+
+impl Default for AdaptiveHuffman {
+    fn default() -> Self {
+        Self::new()
+    }
+}
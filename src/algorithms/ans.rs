@@ -0,0 +1,195 @@
+use crate::bit_map::{BitMap, BitSource};
+use crate::types::FileStats;
+use std::io::{Error, ErrorKind};
+
+// Range variant of asymmetric numeral systems (rANS) with byte-wise renormalization.
+// The whole message is kept in a single integer state: symbol s with normalized frequency f and
+// start c turns state x into (x / f) * M + c + x % f, where M is the sum of all frequencies.
+// Decoder pops symbols in the reverse order, so data is split into blocks which are encoded
+// backwards; decoder finds symbol of a state by a table lookup of its lowest bits.
+
+pub const SCALE_BITS: u32 = 12;
+const TOTAL: u32 = 1 << SCALE_BITS; // normalized frequencies always sum to it
+const LOWER_BOUND: u32 = 1 << 23; // state is kept in [LOWER_BOUND, LOWER_BOUND * 256)
+
+/// symbols encoded at once; the last block of file may be shorter
+pub const BLOCK_SIZE: usize = 1 << 16;
+
+pub struct AnsTable {
+    frequencies: [u32; 256],
+    starts: [u32; 256],
+    slots: Vec<u8>, // slot (lowest SCALE_BITS of state) -> symbol
+}
+
+impl AnsTable {
+    pub fn from_stats(stats: &FileStats) -> Self {
+        let (quantities, total) = stats;
+        let mut frequencies = [0_u32; 256];
+
+        if *total == 0 {
+            return Self::build(frequencies);
+        }
+
+        for (frequency, quantity) in frequencies.iter_mut().zip(quantities) {
+            if *quantity != 0 {
+                let scaled = *quantity as u128 * TOTAL as u128 / *total as u128;
+                *frequency = (scaled as u32).max(1);
+            }
+        }
+
+        // rounding leaves the sum a bit off, it is fixed at the expense of the most frequent symbols
+        let mut sum: u32 = frequencies.iter().sum();
+        while sum != TOTAL {
+            let largest = (0..256).max_by_key(|s| frequencies[*s]).unwrap();
+
+            if sum < TOTAL {
+                frequencies[largest] += TOTAL - sum;
+                sum = TOTAL;
+            } else {
+                frequencies[largest] -= 1;
+                sum -= 1;
+            }
+        }
+
+        Self::build(frequencies)
+    }
+
+    /// frequencies must sum to 2^SCALE_BITS (or all be zero when `symbol_count` is zero)
+    pub fn from_frequencies(frequencies: [u32; 256], symbol_count: u64) -> Result<Self, Error> {
+        let sum: u32 = frequencies.iter().sum();
+        if sum != TOTAL && (sum != 0 || symbol_count != 0) {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("Normalized frequencies sum to {} instead of {}", sum, TOTAL),
+            ));
+        }
+
+        Ok(Self::build(frequencies))
+    }
+
+    pub fn frequencies(&self) -> &[u32; 256] {
+        &self.frequencies
+    }
+
+    fn build(frequencies: [u32; 256]) -> Self {
+        let mut starts = [0; 256];
+        let mut slots = Vec::with_capacity(TOTAL as usize);
+
+        let mut start = 0;
+        for symbol in 0..256 {
+            starts[symbol] = start;
+            start += frequencies[symbol];
+            slots.resize(start as usize, symbol as u8);
+        }
+
+        Self {
+            frequencies,
+            starts,
+            slots,
+        }
+    }
+}
+
+/// collects symbols into blocks and encodes every complete block
+pub struct AnsEncoder {
+    table: AnsTable,
+    pending: Vec<u8>,
+}
+
+impl AnsEncoder {
+    pub fn new(table: AnsTable) -> Self {
+        Self {
+            table,
+            pending: Vec::with_capacity(BLOCK_SIZE),
+        }
+    }
+
+    pub fn encode(&mut self, buf: &[u8], bits: &mut BitMap) {
+        for byte in buf {
+            self.pending.push(*byte);
+
+            if self.pending.len() == BLOCK_SIZE {
+                self.encode_block(bits);
+            }
+        }
+    }
+
+    /// encodes the last incomplete block
+    pub fn finish(&mut self, bits: &mut BitMap) {
+        if !self.pending.is_empty() {
+            self.encode_block(bits);
+        }
+    }
+
+    fn encode_block(&mut self, bits: &mut BitMap) {
+        let mut state = LOWER_BOUND;
+        let mut emitted = Vec::new();
+
+        for symbol in self.pending.iter().rev() {
+            let frequency = self.table.frequencies[*symbol as usize];
+            let start = self.table.starts[*symbol as usize];
+
+            // keep the state small enough for the symbol to fit without overflow
+            let max_state = ((LOWER_BOUND >> SCALE_BITS) << 8) * frequency;
+            while state >= max_state {
+                emitted.push(state as u8);
+                state >>= 8;
+            }
+
+            state = ((state / frequency) << SCALE_BITS) + state % frequency + start;
+        }
+
+        emitted.extend_from_slice(&state.to_be_bytes());
+
+        // decoder reads everything in the reverse order: final state first
+        for byte in emitted.iter().rev() {
+            bits.add_value(*byte as u64, 8);
+        }
+
+        self.pending.clear();
+    }
+}
+
+pub struct AnsDecoder {
+    table: AnsTable,
+    state: u32,
+    left_in_block: usize,
+    remaining: u64,
+}
+
+impl AnsDecoder {
+    pub fn new(table: AnsTable, original_length: u64) -> Self {
+        Self {
+            table,
+            state: 0,
+            left_in_block: 0,
+            remaining: original_length,
+        }
+    }
+
+    pub fn decode(&mut self, bits: &mut impl BitSource) -> Result<u8, Error> {
+        if self.left_in_block == 0 {
+            self.state = u32::from_le_bytes([
+                bits.read_bits(8)? as u8,
+                bits.read_bits(8)? as u8,
+                bits.read_bits(8)? as u8,
+                bits.read_bits(8)? as u8,
+            ]);
+            self.left_in_block = self.remaining.min(BLOCK_SIZE as u64) as usize;
+        }
+
+        let slot = self.state & (TOTAL - 1);
+        let symbol = self.table.slots[slot as usize];
+        let frequency = self.table.frequencies[symbol as usize];
+        let start = self.table.starts[symbol as usize];
+
+        self.state = frequency * (self.state >> SCALE_BITS) + slot - start;
+        while self.state < LOWER_BOUND {
+            self.state = (self.state << 8) | bits.read_bits(8)? as u32;
+        }
+
+        self.left_in_block -= 1;
+        self.remaining -= 1;
+        Ok(symbol)
+    }
+}
@@ -0,0 +1,260 @@
+use crate::bit_map::{BitMap, BitSource};
+use crate::types::FileStats;
+use std::io::{Error, ErrorKind};
+
+// Integer arithmetic coding with 32-bit interval (Witten, Neal and Cleary). The interval
+// [low, high] is narrowed by every symbol proportionally to its frequency, leading bits that can
+// no longer change are emitted. When interval straddles the middle and gets too small, its
+// middle half is expanded and decision about emitted bit is postponed ("pending" bits).
+
+const PRECISION: u32 = 32;
+const FULL: u64 = 1 << PRECISION;
+const HALF: u64 = FULL / 2;
+const QUARTER: u64 = FULL / 4;
+
+/// frequencies are scaled to this total, so they always fit into 2 bytes of header
+pub const MAX_TOTAL: u32 = 1 << 15;
+const ADAPTIVE_INCREMENT: u32 = 32;
+
+/// frequencies of symbols; static model is built once from statistics of the file, adaptive one
+/// starts with all symbols equally probable and is updated after every coded symbol
+#[derive(Clone)]
+pub struct FrequencyModel {
+    frequencies: [u32; 256],
+    cumulative: [u32; 257], // cumulative[s] - sum of frequencies of symbols less than s
+    adaptive: bool,
+}
+
+impl FrequencyModel {
+    pub fn from_stats(stats: &FileStats) -> Self {
+        let (quantities, total) = stats;
+        let mut frequencies = [0; 256];
+
+        for (frequency, quantity) in frequencies.iter_mut().zip(quantities) {
+            if *quantity != 0 {
+                // every present symbol must keep non-zero frequency
+                let scaled = *quantity as u128 * (MAX_TOTAL - 256) as u128 / *total as u128;
+                *frequency = (scaled as u32).max(1);
+            }
+        }
+
+        Self::from_frequencies(frequencies)
+    }
+
+    /// frequencies must not sum over MAX_TOTAL
+    pub fn from_frequencies(frequencies: [u32; 256]) -> Self {
+        let mut model = Self {
+            frequencies,
+            cumulative: [0; 257],
+            adaptive: false,
+        };
+        model.accumulate();
+
+        model
+    }
+
+    pub fn adaptive() -> Self {
+        let mut model = Self::from_frequencies([1; 256]);
+        model.adaptive = true;
+
+        model
+    }
+
+    pub fn frequencies(&self) -> &[u32; 256] {
+        &self.frequencies
+    }
+
+    pub fn total(&self) -> u32 {
+        self.cumulative[256]
+    }
+
+    /// cumulative frequencies bounding the symbol
+    pub fn range(&self, symbol: u8) -> (u32, u32) {
+        (
+            self.cumulative[symbol as usize],
+            self.cumulative[symbol as usize + 1],
+        )
+    }
+
+    /// symbol whose range contains target
+    pub fn symbol_for(&self, target: u32) -> u8 {
+        (self.cumulative.partition_point(|c| *c <= target) - 1) as u8
+    }
+
+    pub fn update(&mut self, symbol: u8) {
+        if !self.adaptive {
+            return;
+        }
+
+        self.frequencies[symbol as usize] += ADAPTIVE_INCREMENT;
+        if self.total() + ADAPTIVE_INCREMENT > MAX_TOTAL {
+            for frequency in self.frequencies.iter_mut() {
+                *frequency = (*frequency / 2).max(1);
+            }
+        }
+
+        self.accumulate();
+    }
+
+    fn accumulate(&mut self) {
+        for symbol in 0..256 {
+            self.cumulative[symbol + 1] = self.cumulative[symbol] + self.frequencies[symbol];
+        }
+    }
+}
+
+pub struct ArithmeticEncoder {
+    low: u64,
+    high: u64,
+    pending: u64,
+}
+
+impl ArithmeticEncoder {
+    pub fn new() -> Self {
+        Self {
+            low: 0,
+            high: FULL - 1,
+            pending: 0,
+        }
+    }
+
+    pub fn encode_symbol(&mut self, model: &mut FrequencyModel, symbol: u8, bits: &mut BitMap) {
+        let (cum_low, cum_high) = model.range(symbol);
+        self.encode(cum_low, cum_high, model.total(), bits);
+        model.update(symbol);
+    }
+
+    /// narrows interval to [cum_low, cum_high) out of total
+    pub fn encode(&mut self, cum_low: u32, cum_high: u32, total: u32, bits: &mut BitMap) {
+        let range = self.high - self.low + 1;
+        self.high = self.low + range * cum_high as u64 / total as u64 - 1;
+        self.low += range * cum_low as u64 / total as u64;
+
+        loop {
+            if self.high < HALF {
+                self.emit(0, bits);
+            } else if self.low >= HALF {
+                self.emit(1, bits);
+                self.low -= HALF;
+                self.high -= HALF;
+            } else if self.low >= QUARTER && self.high < HALF + QUARTER {
+                self.pending += 1;
+                self.low -= QUARTER;
+                self.high -= QUARTER;
+            } else {
+                break;
+            }
+
+            self.low *= 2;
+            self.high = self.high * 2 + 1;
+        }
+    }
+
+    /// emits bits that select a value inside of the final interval
+    pub fn finish(&mut self, bits: &mut BitMap) {
+        self.pending += 1;
+        if self.low < QUARTER {
+            self.emit(0, bits);
+        } else {
+            self.emit(1, bits);
+        }
+    }
+
+    fn emit(&mut self, bit: u8, bits: &mut BitMap) {
+        bits.add_bit(bit);
+        for _ in 0..self.pending {
+            bits.add_bit(1 - bit);
+        }
+        self.pending = 0;
+    }
+}
+
+impl Default for ArithmeticEncoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub struct ArithmeticDecoder {
+    low: u64,
+    high: u64,
+    value: u64,
+}
+
+impl ArithmeticDecoder {
+    pub fn new(bits: &mut impl BitSource) -> Result<Self, Error> {
+        let mut value = 0;
+        for _ in 0..PRECISION {
+            value = (value << 1) | next_bit(bits)? as u64;
+        }
+
+        Ok(Self {
+            low: 0,
+            high: FULL - 1,
+            value,
+        })
+    }
+
+    pub fn decode_symbol(
+        &mut self,
+        model: &mut FrequencyModel,
+        bits: &mut impl BitSource,
+    ) -> Result<u8, Error> {
+        let symbol = model.symbol_for(self.target(model.total()));
+        let (cum_low, cum_high) = model.range(symbol);
+
+        self.consume(cum_low, cum_high, model.total(), bits)?;
+        model.update(symbol);
+
+        Ok(symbol)
+    }
+
+    /// cumulative frequency pointed by current value, symbol is the one whose range contains it
+    pub fn target(&self, total: u32) -> u32 {
+        let range = self.high - self.low + 1;
+        (((self.value - self.low + 1) * total as u64 - 1) / range) as u32
+    }
+
+    /// repeats narrowing of interval made by encoder for decoded symbol
+    pub fn consume(
+        &mut self,
+        cum_low: u32,
+        cum_high: u32,
+        total: u32,
+        bits: &mut impl BitSource,
+    ) -> Result<(), Error> {
+        let range = self.high - self.low + 1;
+        self.high = self.low + range * cum_high as u64 / total as u64 - 1;
+        self.low += range * cum_low as u64 / total as u64;
+
+        loop {
+            if self.high < HALF {
+                // nothing to subtract
+            } else if self.low >= HALF {
+                self.low -= HALF;
+                self.high -= HALF;
+                self.value -= HALF;
+            } else if self.low >= QUARTER && self.high < HALF + QUARTER {
+                self.low -= QUARTER;
+                self.high -= QUARTER;
+                self.value -= QUARTER;
+            } else {
+                break;
+            }
+
+            self.low *= 2;
+            self.high = self.high * 2 + 1;
+            self.value = self.value * 2 + next_bit(bits)? as u64;
+        }
+
+        Ok(())
+    }
+}
+
+/// encoder does not emit trailing zeros, so decoder reads them past the end of data
+fn next_bit(bits: &mut impl BitSource) -> Result<u8, Error> {
+    match bits.read_bit() {
+        Err(err) if err.kind() == ErrorKind::UnexpectedEof => Ok(0),
+        result => result,
+    }
+}
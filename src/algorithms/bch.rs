@@ -0,0 +1,308 @@
+use super::hamming::ParityReport;
+use std::cmp::min;
+use std::io::{Error, ErrorKind};
+
+// Primitive binary BCH code: blocks of n = 2^m - 1 bits that correct up to t flipped bits
+// (Hamming code is the one with t = 1). Its generator polynomial is the least common multiple of
+// minimal polynomials of a^1, a^3, ..., a^(2t - 1), where a is a primitive element of GF(2^m);
+// minimal polynomial of a^i is the product of (x - a^j) over its conjugates j = i * 2^s mod n.
+// Block is k = n - deg(generator) data bits followed by the remainder of data * x^(n - k) divided by
+// generator (systematic encoding). Bit of block at index i is a coefficient of x^(n - 1 - i).
+// Decoding: syndromes S_j = block(a^j), j = 1..2t -> error locator with Berlekamp-Massey ->
+// positions of errors with Chien search (roots of locator are inverse locators a^-(n - 1 - i)).
+// Flipping the bits is the whole correction, values of binary errors are always one.
+
+pub const MIN_FIELD_BITS: u8 = 3;
+pub const MAX_FIELD_BITS: u8 = 10;
+
+/// primitive polynomials of GF(2^m) for m from `MIN_FIELD_BITS`
+const PRIMITIVE_POLYNOMIALS: [u16; 8] = [0xb, 0x13, 0x25, 0x43, 0x89, 0x11d, 0x211, 0x409];
+
+pub fn check_parameters(code_length: usize, t: usize) -> Result<(), Error> {
+    let field_bits = (code_length + 1).trailing_zeros() as u8;
+    if !(code_length + 1).is_power_of_two()
+        || !(MIN_FIELD_BITS..=MAX_FIELD_BITS).contains(&field_bits)
+    {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!(
+                "BCH block must be 2^m - 1 bits long with m from {} to {}, got {}",
+                MIN_FIELD_BITS, MAX_FIELD_BITS, code_length
+            ),
+        ));
+    }
+    // 2t consecutive roots must be distinct powers of the primitive element
+    if t == 0 || 2 * t >= code_length {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!(
+                "BCH block of {} bits can not correct {} errors",
+                code_length, t
+            ),
+        ));
+    }
+
+    Ok(())
+}
+
+pub struct Bch {
+    n: usize,
+    k: usize,
+    t: usize,
+    exp: Vec<usize>, // powers of primitive element, doubled to skip reduction modulo n
+    log: Vec<usize>,
+    generator: Vec<u8>, // binary coefficients from the highest power
+}
+
+impl Bch {
+    pub fn new(code_length: usize, t: usize) -> Result<Self, Error> {
+        check_parameters(code_length, t)?;
+        let n = code_length;
+        let field_bits = (n + 1).trailing_zeros() as usize;
+        let primitive = PRIMITIVE_POLYNOMIALS[field_bits - MIN_FIELD_BITS as usize] as usize;
+
+        let mut exp = vec![0; 2 * n];
+        let mut log = vec![0; n + 1];
+        let mut value = 1;
+        for power in 0..n {
+            exp[power] = value;
+            exp[power + n] = value;
+            log[value] = power;
+            value <<= 1;
+            if value > n {
+                value ^= primitive;
+            }
+        }
+
+        let mut code = Self {
+            n,
+            k: 0,
+            t,
+            exp,
+            log,
+            generator: vec![],
+        };
+
+        // every conjugacy class brings its minimal polynomial once
+        let mut generator = vec![1];
+        let mut used = vec![false; n];
+        for root in (1..2 * t).step_by(2) {
+            if used[root] {
+                continue;
+            }
+
+            let mut minimal = vec![1];
+            let mut conjugate = root;
+            while !used[conjugate] {
+                used[conjugate] = true;
+                minimal = code.multiply_polynomials(&minimal, &[1, code.exp[conjugate]]);
+                conjugate = 2 * conjugate % n;
+            }
+            generator = code.multiply_polynomials(&generator, &minimal);
+        }
+
+        if generator.len() > n {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("BCH block of {} bits can not correct {} errors", n, t),
+            ));
+        }
+        code.k = n + 1 - generator.len();
+        // minimal polynomials and their product have binary coefficients
+        code.generator = generator.into_iter().map(|c| c as u8).collect();
+
+        Ok(code)
+    }
+
+    pub fn code_length(&self) -> usize {
+        self.n
+    }
+
+    pub fn data_length(&self) -> usize {
+        self.k
+    }
+
+    pub fn correctable_errors(&self) -> usize {
+        self.t
+    }
+
+    pub fn generator(&self) -> &[u8] {
+        &self.generator
+    }
+
+    fn multiply(&self, a: usize, b: usize) -> usize {
+        match (a, b) {
+            (0, _) | (_, 0) => 0,
+            _ => self.exp[self.log[a] + self.log[b]],
+        }
+    }
+
+    fn divide(&self, a: usize, b: usize) -> usize {
+        assert_ne!(b, 0, "division by zero in GF(2^m)");
+        match a {
+            0 => 0,
+            _ => self.exp[self.log[a] + self.n - self.log[b]],
+        }
+    }
+
+    /// both polynomials and the result go from the same end
+    fn multiply_polynomials(&self, p: &[usize], q: &[usize]) -> Vec<usize> {
+        let mut product = vec![0; p.len() + q.len() - 1];
+        for (i, a) in p.iter().enumerate() {
+            for (j, b) in q.iter().enumerate() {
+                product[i + j] ^= self.multiply(*a, *b);
+            }
+        }
+
+        product
+    }
+
+    /// `data` must be `k` bits long, returns block of `n` bits that starts with the data
+    pub fn encode(&self, data: &[u8]) -> Vec<u8> {
+        assert_eq!(data.len(), self.k, "BCH block takes exactly k bits");
+
+        // remainder of data * x^(n - k) divided by generator
+        let mut remainder = data.to_vec();
+        remainder.resize(self.n, 0);
+        for i in 0..self.k {
+            if remainder[i] == 1 {
+                for (j, g) in self.generator.iter().enumerate() {
+                    remainder[i + j] ^= g;
+                }
+            }
+        }
+
+        let mut block = data.to_vec();
+        block.extend_from_slice(&remainder[self.k..]);
+        block
+    }
+
+    /// corrects the block in place, returns indices of flipped bits
+    pub fn decode(&self, block: &mut [u8]) -> Result<Vec<usize>, Error> {
+        if block.len() != self.n {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("BCH block must be {} bits long", self.n),
+            ));
+        }
+
+        let syndromes: Vec<usize> = (1..=2 * self.t)
+            .map(|power| {
+                block
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, bit)| **bit == 1)
+                    .fold(0, |s, (i, _)| {
+                        s ^ self.exp[(self.n - 1 - i) * power % self.n]
+                    })
+            })
+            .collect();
+        if syndromes.iter().all(|s| *s == 0) {
+            return Ok(vec![]);
+        }
+
+        let locator = self.berlekamp_massey(&syndromes)?;
+
+        // Chien search: locator of bit i is a^(n - 1 - i), roots of error locator are their inverses
+        let positions: Vec<usize> = (0..self.n)
+            .filter(|i| {
+                let x = self.exp[(i + 1) % self.n];
+                let value = locator
+                    .iter()
+                    .rev()
+                    .fold(0, |value, c| self.multiply(value, x) ^ c);
+                value == 0
+            })
+            .collect();
+        if positions.len() != locator.len() - 1 {
+            return Err(too_many_errors());
+        }
+
+        for position in &positions {
+            block[*position] ^= 1;
+        }
+        Ok(positions)
+    }
+
+    /// error locator from the lowest power; `syndromes` start from S_1
+    fn berlekamp_massey(&self, syndromes: &[usize]) -> Result<Vec<usize>, Error> {
+        let mut locator = vec![1];
+        let mut previous = vec![1];
+        let mut length = 0;
+
+        for step in 0..syndromes.len() {
+            let discrepancy = locator
+                .iter()
+                .enumerate()
+                .take(step + 1)
+                .fold(0, |d, (i, c)| d ^ self.multiply(*c, syndromes[step - i]));
+
+            previous.insert(0, 0);
+            if discrepancy == 0 {
+                continue;
+            }
+
+            let mut next = locator.clone();
+            next.resize(next.len().max(previous.len()), 0);
+            for (i, c) in previous.iter().enumerate() {
+                next[i] ^= self.multiply(discrepancy, *c);
+            }
+
+            if 2 * length <= step {
+                length = step + 1 - length;
+                previous = locator
+                    .iter()
+                    .map(|c| self.divide(*c, discrepancy))
+                    .collect();
+            }
+            locator = next;
+        }
+
+        while locator.len() > 1 && locator[locator.len() - 1] == 0 {
+            locator.pop();
+        }
+        if locator.len() - 1 != length || length > self.t {
+            return Err(too_many_errors());
+        }
+
+        Ok(locator)
+    }
+
+    /// splits package into blocks of `k` bits, the last one is padded with zeros
+    pub fn add_parity_package(&self, package: &[u8]) -> Vec<u8> {
+        let mut encoded = vec![];
+        for data_ptr in (0..package.len()).step_by(self.k) {
+            let mut data = package[data_ptr..min(data_ptr + self.k, package.len())].to_vec();
+            data.resize(self.k, 0);
+            encoded.append(&mut self.encode(&data));
+        }
+
+        encoded
+    }
+
+    /// package must consist of whole blocks; data of blocks that can not be corrected is left as is
+    pub fn remove_parity_package(&self, package: &mut [u8]) -> (Vec<u8>, ParityReport) {
+        let mut decoded = vec![];
+        let mut report = ParityReport::default();
+
+        for (block_index, block) in package.chunks_mut(self.n).enumerate() {
+            match self.decode(block) {
+                Ok(positions) => report
+                    .corrected
+                    .extend(positions.into_iter().map(|index| (block_index, index))),
+                Err(_) => report.uncorrectable.push(block_index),
+            }
+            report.blocks_checked += 1;
+            decoded.extend_from_slice(&block[..self.k]);
+        }
+
+        (decoded, report)
+    }
+}
+
+fn too_many_errors() -> Error {
+    Error::new(
+        ErrorKind::InvalidData,
+        "BCH block has more errors than it can correct",
+    )
+}
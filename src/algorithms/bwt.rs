@@ -0,0 +1,243 @@
+use std::io::{Error, ErrorKind};
+
+// Block-sorting pipeline in the style of bzip2:
+// 1. Burrows-Wheeler transform groups bytes that precede similar contexts together. It is taken
+//    from the suffix array of a block with an implicit sentinel (smaller than any byte) at its
+//    end; the sentinel is dropped from output and its row ("primary index") is kept instead.
+// 2. Move-to-front turns those groups into runs of zeros and other small numbers.
+// 3. Runs of zeros are replaced by their lengths.
+// The result is left for an entropy coder.
+
+/// bytes transformed at once; the last block of file may be shorter
+pub const BLOCK_SIZE: usize = 1 << 19;
+/// longest run of zeros in a single token, longer runs are split
+pub const MAX_RUN: usize = 256;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Symbol {
+    Value(u8), // non-zero move-to-front output
+    ZeroRun(usize),
+}
+
+/// gathers chunks of data into blocks of BLOCK_SIZE
+pub struct Blocks {
+    pending: Vec<u8>,
+}
+
+impl Blocks {
+    pub fn new() -> Self {
+        Self {
+            pending: Vec::new(),
+        }
+    }
+
+    /// passes every complete block (and the rest of data on its end) to `on_block`
+    pub fn push<F>(&mut self, buf: &[u8], end_of_data: bool, mut on_block: F)
+    where
+        F: FnMut(&[u8]),
+    {
+        let mut buf = buf;
+        while !buf.is_empty() {
+            let taken = buf.len().min(BLOCK_SIZE - self.pending.len());
+            self.pending.extend_from_slice(&buf[..taken]);
+            buf = &buf[taken..];
+
+            if self.pending.len() == BLOCK_SIZE {
+                on_block(&self.pending);
+                self.pending.clear();
+            }
+        }
+
+        if end_of_data && !self.pending.is_empty() {
+            on_block(&self.pending);
+            self.pending.clear();
+        }
+    }
+}
+
+impl Default for Blocks {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// runs the whole pipeline over a block, returns primary index and symbols
+pub fn encode_block(block: &[u8]) -> (usize, Vec<Symbol>) {
+    let (last_column, primary) = transform(block);
+    (primary, encode_zero_runs(&move_to_front(&last_column)))
+}
+
+/// reverts `encode_block` given move-to-front values with expanded runs of zeros
+pub fn decode_block(values: &[u8], primary: usize) -> Result<Vec<u8>, Error> {
+    inverse_transform(&inverse_move_to_front(values), primary)
+}
+
+/// returns last column of sorted rotations without the sentinel and the row of the sentinel
+pub fn transform(block: &[u8]) -> (Vec<u8>, usize) {
+    let mut last_column = Vec::with_capacity(block.len());
+    let mut primary = 0;
+
+    for (row, start) in suffix_array(block).into_iter().enumerate() {
+        match start {
+            0 => primary = row,
+            _ => last_column.push(block[start - 1]),
+        }
+    }
+
+    (last_column, primary)
+}
+
+pub fn inverse_transform(last_column: &[u8], primary: usize) -> Result<Vec<u8>, Error> {
+    let length = last_column.len();
+    if length == 0 {
+        return Ok(vec![]);
+    }
+    if primary == 0 || primary > length {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            format!(
+                "Primary index {} is out of block of {} bytes",
+                primary, length
+            ),
+        ));
+    }
+
+    // rows in the first column start with the sentinel followed by bytes in sorted order
+    let mut counts = [0; 256];
+    for byte in last_column {
+        counts[*byte as usize] += 1;
+    }
+
+    let mut starts = [0; 256];
+    let mut smaller = 1;
+    for (start, count) in starts.iter_mut().zip(counts) {
+        *start = smaller;
+        smaller += count;
+    }
+
+    // row of rotation that starts with the byte at the end of the given row
+    let mut last_to_first = vec![0; length + 1];
+    let mut row = 0;
+    for byte in last_column {
+        if row == primary {
+            row += 1; // the row ending with the sentinel is never visited
+        }
+        last_to_first[row] = starts[*byte as usize];
+        starts[*byte as usize] += 1;
+        row += 1;
+    }
+
+    let mut block = vec![0; length];
+    let mut row = 0;
+    for position in (0..length).rev() {
+        let index = if row < primary { row } else { row - 1 };
+        block[position] = last_column[index];
+        row = last_to_first[row];
+    }
+
+    Ok(block)
+}
+
+/// starts of suffixes of data followed by the sentinel in sorted order (so the first one is the
+/// sentinel alone); built by prefix doubling with counting sort
+fn suffix_array(data: &[u8]) -> Vec<usize> {
+    let length = data.len() + 1;
+
+    // class of a suffix is its rank by the first `prefix` symbols; sentinel takes class 0
+    let mut classes: Vec<usize> = data.iter().map(|b| *b as usize + 1).collect();
+    classes.push(0);
+    let mut suffixes: Vec<usize> = (0..length).collect();
+    suffixes.sort_by_key(|i| classes[*i]);
+
+    let mut class_count = 257;
+    let mut shifted = vec![0; length];
+    let mut next_classes = vec![0; length];
+    let mut prefix = 1;
+    loop {
+        // pairs of halves (i, i + prefix) are sorted by the second half already, the stable
+        // counting sort by the first half finishes the job (suffixes wrap around the sentinel)
+        for (shifted, suffix) in shifted.iter_mut().zip(&suffixes) {
+            *shifted = (suffix + length - prefix % length) % length;
+        }
+
+        let mut starts = vec![0; class_count + 1];
+        for suffix in &shifted {
+            starts[classes[*suffix] + 1] += 1;
+        }
+        for class in 0..class_count {
+            starts[class + 1] += starts[class];
+        }
+        for suffix in &shifted {
+            suffixes[starts[classes[*suffix]]] = *suffix;
+            starts[classes[*suffix]] += 1;
+        }
+
+        let key = |i: usize| (classes[i], classes[(i + prefix) % length]);
+        next_classes[suffixes[0]] = 0;
+        for pair in suffixes.windows(2) {
+            let differs = key(pair[0]) != key(pair[1]);
+            next_classes[pair[1]] = next_classes[pair[0]] + differs as usize;
+        }
+        std::mem::swap(&mut classes, &mut next_classes);
+
+        class_count = classes[suffixes[length - 1]] + 1;
+        if class_count == length {
+            break;
+        }
+        prefix *= 2;
+    }
+
+    suffixes
+}
+
+pub fn move_to_front(data: &[u8]) -> Vec<u8> {
+    let mut order: Vec<u8> = (0..=255).collect();
+
+    data.iter()
+        .map(|byte| {
+            let index = order.iter().position(|b| b == byte).unwrap();
+            order[..=index].rotate_right(1);
+            index as u8
+        })
+        .collect()
+}
+
+pub fn inverse_move_to_front(values: &[u8]) -> Vec<u8> {
+    let mut order: Vec<u8> = (0..=255).collect();
+
+    values
+        .iter()
+        .map(|value| {
+            let index = *value as usize;
+            order[..=index].rotate_right(1);
+            order[0]
+        })
+        .collect()
+}
+
+pub fn encode_zero_runs(values: &[u8]) -> Vec<Symbol> {
+    let mut symbols = vec![];
+    let mut run = 0;
+
+    for value in values {
+        if *value == 0 {
+            run += 1;
+            if run == MAX_RUN {
+                symbols.push(Symbol::ZeroRun(run));
+                run = 0;
+            }
+            continue;
+        }
+
+        if run != 0 {
+            symbols.push(Symbol::ZeroRun(run));
+            run = 0;
+        }
+        symbols.push(Symbol::Value(*value));
+    }
+
+    if run != 0 {
+        symbols.push(Symbol::ZeroRun(run));
+    }
+    symbols
+}
@@ -0,0 +1,160 @@
+use crate::bit_map::BitSource;
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::io::{Error, ErrorKind};
+
+// Symbols are bytes in most coders, n-grams and words are coded as integers of wider types.
+
+/// replaces codes of any prefix code by canonical ones of the same lengths
+pub fn canonize<S: Copy + Ord + Hash>(codes: &HashMap<S, Vec<u8>>) -> HashMap<S, Vec<u8>> {
+    let lengths = codes
+        .iter()
+        .map(|(symbol, code)| (*symbol, code.len()))
+        .collect();
+
+    assign_codes(&lengths)
+}
+
+/// symbols paired with their code lengths, sorted by length and then by symbol,
+/// which is exactly the order canonical codes are assigned in
+pub fn canonical_order<S: Copy + Ord + Hash>(lengths: &HashMap<S, usize>) -> Vec<(S, usize)> {
+    let mut order: Vec<(S, usize)> = lengths.iter().map(|(s, l)| (*s, *l)).collect();
+    order.sort_by_key(|(symbol, length)| (*length, *symbol));
+
+    order
+}
+
+/// amount of codes of every length from 1 to the longest one
+pub fn length_counts<S>(lengths: &HashMap<S, usize>) -> Vec<usize> {
+    let max_length = lengths.values().max().copied().unwrap_or(0);
+
+    let mut counts = vec![0; max_length];
+    for length in lengths.values() {
+        counts[length - 1] += 1;
+    }
+
+    counts
+}
+
+/// builds canonical code: every next code is previous plus one, shifted left when length grows
+pub fn assign_codes<S: Copy + Ord + Hash>(lengths: &HashMap<S, usize>) -> HashMap<S, Vec<u8>> {
+    assign_d_ary_codes(lengths, 2)
+}
+
+/// same as `assign_codes` for codes made of digits from 0 to `arity - 1`
+pub fn assign_d_ary_codes<S: Copy + Ord + Hash>(
+    lengths: &HashMap<S, usize>,
+    arity: u8,
+) -> HashMap<S, Vec<u8>> {
+    let mut codes = HashMap::new();
+
+    let mut code: u128 = 0;
+    let mut prev_length = 0;
+
+    for (symbol, length) in canonical_order(lengths) {
+        code *= (arity as u128).pow((length - prev_length) as u32);
+        codes.insert(symbol, code_to_digits(code, length, arity));
+
+        code += 1;
+        prev_length = length;
+    }
+
+    codes
+}
+
+/// most significant digit goes first
+fn code_to_digits(code: u128, length: usize, arity: u8) -> Vec<u8> {
+    let mut digits = vec![0; length];
+    let mut rest = code;
+    for digit in digits.iter_mut().rev() {
+        *digit = (rest % arity as u128) as u8;
+        rest /= arity as u128;
+    }
+
+    digits
+}
+
+/// decodes canonical code bit by bit knowing only lengths of the codes
+pub struct CanonicalDecoder<S = u8> {
+    arity: u128,
+    first_code: Vec<u128>, // first code of each length
+    count: Vec<usize>,     // amount of codes of each length
+    offset: Vec<usize>,    // index in `symbols` of first symbol with code of each length
+    symbols: Vec<S>,
+    code: u128,
+    length: usize,
+}
+
+impl<S: Copy + Ord + Hash> CanonicalDecoder<S> {
+    pub fn new(lengths: &HashMap<S, usize>) -> Self {
+        Self::with_arity(lengths, 2)
+    }
+
+    /// decoder of code made of digits from 0 to `arity - 1`
+    pub fn with_arity(lengths: &HashMap<S, usize>, arity: u8) -> Self {
+        let arity = arity as u128;
+        let order = canonical_order(lengths);
+        let max_length = order.last().map(|(_, l)| *l).unwrap_or(0);
+
+        let mut count = vec![0; max_length + 1];
+        for (_, length) in &order {
+            count[*length] += 1;
+        }
+
+        let mut first_code = vec![0; max_length + 1];
+        let mut offset = vec![0; max_length + 1];
+        let mut code: u128 = 0;
+        for length in 1..=max_length {
+            code = (code + count[length - 1] as u128) * arity;
+            first_code[length] = code;
+            offset[length] = offset[length - 1] + count[length - 1];
+        }
+
+        Self {
+            arity,
+            first_code,
+            count,
+            offset,
+            symbols: order.iter().map(|(s, _)| *s).collect(),
+            code: 0,
+            length: 0,
+        }
+    }
+
+    pub fn decode(&mut self, bits: &mut impl BitSource) -> Result<S, Error> {
+        loop {
+            if let Some(symbol) = self.push_bit(bits.read_bit()?)? {
+                return Ok(symbol);
+            }
+        }
+    }
+
+    /// returns decoded symbol once the bit completes a code
+    pub fn push_bit(&mut self, bit: u8) -> Result<Option<S>, Error> {
+        self.push_digit(bit)
+    }
+
+    /// returns decoded symbol once the digit completes a code
+    pub fn push_digit(&mut self, digit: u8) -> Result<Option<S>, Error> {
+        self.code = self.code * self.arity + digit as u128;
+        self.length += 1;
+
+        if self.length >= self.count.len() {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                "Bit sequence does not match any code of the dictionary",
+            ));
+        }
+
+        let first_code = self.first_code[self.length];
+        if self.code >= first_code && self.code - first_code < self.count[self.length] as u128 {
+            let index = self.offset[self.length] + (self.code - first_code) as usize;
+
+            self.code = 0;
+            self.length = 0;
+            return Ok(Some(self.symbols[index]));
+        }
+
+        Ok(None)
+    }
+}
@@ -0,0 +1,147 @@
+use super::canonical::{self, CanonicalDecoder};
+use super::huffman;
+use super::universal;
+use crate::bit_map::BitSource;
+use std::collections::HashMap;
+use std::io::Error;
+
+// Order-1 context modeling: every byte is coded with Huffman code built for the byte before it
+// (the first byte of file follows INITIAL_CONTEXT). Own table of a context costs space in the
+// header, so contexts where it does not pay off are merged into one shared table.
+
+pub const INITIAL_CONTEXT: u8 = 0;
+
+/// previous byte -> quantities of bytes that follow it
+pub struct ContextStats {
+    quantities: Vec<[u64; 256]>,
+    previous: u8,
+}
+
+impl ContextStats {
+    pub fn new() -> Self {
+        Self {
+            quantities: vec![[0; 256]; 256],
+            previous: INITIAL_CONTEXT,
+        }
+    }
+
+    pub fn update(&mut self, buf: &[u8]) {
+        for byte in buf {
+            self.quantities[self.previous as usize][*byte as usize] += 1;
+            self.previous = *byte;
+        }
+    }
+
+    pub fn quantities(&self, context: u8) -> &[u64; 256] {
+        &self.quantities[context as usize]
+    }
+}
+
+impl Default for ContextStats {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// canonical codes for every context; `own[context]` is None for merged contexts
+pub struct ContextCodes {
+    pub shared: HashMap<u8, Vec<u8>>,
+    pub own: Vec<Option<HashMap<u8, Vec<u8>>>>,
+}
+
+impl ContextCodes {
+    pub fn build(stats: &ContextStats) -> Self {
+        let mut order_0 = [0; 256];
+        for context in 0..=255 {
+            for (total, quantity) in order_0.iter_mut().zip(stats.quantities(context)) {
+                *total += quantity;
+            }
+        }
+        let order_0_codes = build_code(&order_0);
+
+        let mut shared_quantities = [0; 256];
+        let mut own = vec![None; 256];
+
+        for context in 0..=255 {
+            let quantities = stats.quantities(context);
+            let codes = build_code(quantities);
+
+            // gain is estimated against order-0 code, as the shared one is not known yet
+            let mut saved_bits: i64 = 0;
+            for (symbol, code) in &codes {
+                let quantity = quantities[*symbol as usize] as i64;
+                saved_bits += quantity * (order_0_codes[symbol].len() as i64 - code.len() as i64);
+            }
+
+            if saved_bits > table_size(&codes) as i64 * 8 {
+                own[context as usize] = Some(codes);
+            } else {
+                for (total, quantity) in shared_quantities.iter_mut().zip(quantities) {
+                    *total += quantity;
+                }
+            }
+        }
+
+        Self {
+            shared: build_code(&shared_quantities),
+            own,
+        }
+    }
+
+    pub fn code(&self, context: u8, symbol: u8) -> &Vec<u8> {
+        match &self.own[context as usize] {
+            Some(codes) => &codes[&symbol],
+            None => &self.shared[&symbol],
+        }
+    }
+}
+
+/// decodes bytes one by one, following the context of the previous byte
+pub struct ContextDecoder {
+    shared: CanonicalDecoder,
+    own: Vec<Option<CanonicalDecoder>>,
+    previous: u8,
+}
+
+impl ContextDecoder {
+    pub fn new(shared: &HashMap<u8, usize>, own: &[Option<HashMap<u8, usize>>]) -> Self {
+        Self {
+            shared: CanonicalDecoder::new(shared),
+            own: own
+                .iter()
+                .map(|lengths| lengths.as_ref().map(CanonicalDecoder::new))
+                .collect(),
+            previous: INITIAL_CONTEXT,
+        }
+    }
+
+    pub fn decode(&mut self, bits: &mut impl BitSource) -> Result<u8, Error> {
+        let byte = match &mut self.own[self.previous as usize] {
+            Some(decoder) => decoder.decode(bits)?,
+            None => self.shared.decode(bits)?,
+        };
+
+        self.previous = byte;
+        Ok(byte)
+    }
+}
+
+fn build_code(quantities: &[u64; 256]) -> HashMap<u8, Vec<u8>> {
+    canonical::canonize(&huffman::encode((*quantities, quantities.iter().sum())))
+}
+
+/// bytes taken by the table in archive header (same layout as file dictionary)
+fn table_size(codes: &HashMap<u8, Vec<u8>>) -> usize {
+    let lengths = codes
+        .iter()
+        .map(|(symbol, code)| (*symbol, code.len()))
+        .collect();
+    let counts = canonical::length_counts(&lengths);
+
+    let bits = universal::exp_golomb_length(counts.len() as u64, 0)
+        + counts
+            .iter()
+            .map(|count| universal::exp_golomb_length(*count as u64, 0))
+            .sum::<usize>();
+    bits.div_ceil(8) + codes.len()
+}
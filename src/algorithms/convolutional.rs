@@ -0,0 +1,223 @@
+use crate::bit_map::{BitMap, BitSource};
+use std::io::{Error, ErrorKind};
+
+// Convolutional code: every input bit is shifted into a register of K bits (K is constraint length)
+// and every generator polynomial gives an output bit, the parity of register bits it taps.
+// Bit K - 1 of a polynomial taps the current input, bit 0 taps the oldest one; polynomials are
+// usually written in octal, e.g. 171 and 133 for K = 7. Input is followed by K - 1 zeros (tail),
+// so the register ends in zero state and the last bits are protected as well as the rest.
+// Puncturing drops some output bits to raise the rate: row j of the pattern belongs to output j,
+// column i to every input bit whose index is i modulo pattern period, 0 - the bit is not sent.
+// Viterbi decoder walks the trellis of 2^(K - 1) register states keeping for every state the most
+// likely path into it. Received bits are log-likelihood ratios log(P(0) / P(1)): a hard bit is
+// +1 or -1, a punctured one is 0; a path gains the ratio for every 0 it expects and loses it for
+// every 1, so hard decision decoding finds the path with the least Hamming distance.
+
+pub const MIN_CONSTRAINT_LENGTH: u8 = 2;
+pub const MAX_CONSTRAINT_LENGTH: u8 = 9;
+pub const MAX_OUTPUTS: usize = 4;
+pub const MAX_PUNCTURING_PERIOD: usize = 16;
+/// data bits of a frame, frames are coded independently when the code protects archive data
+pub const FRAME_LENGTH: usize = 2048;
+
+pub struct ConvolutionalCode {
+    constraint_length: usize,
+    polynomials: Vec<u16>,
+    puncturing: Option<Vec<Vec<u8>>>,
+}
+
+pub fn check_parameters(
+    constraint_length: u8,
+    polynomials: &[u16],
+    puncturing: Option<&Vec<Vec<u8>>>,
+) -> Result<(), Error> {
+    let invalid = |message: String| Err(Error::new(ErrorKind::InvalidInput, message));
+
+    if !(MIN_CONSTRAINT_LENGTH..=MAX_CONSTRAINT_LENGTH).contains(&constraint_length) {
+        return invalid(format!(
+            "Constraint length must be from {} to {}, got {}",
+            MIN_CONSTRAINT_LENGTH, MAX_CONSTRAINT_LENGTH, constraint_length
+        ));
+    }
+    if !(2..=MAX_OUTPUTS).contains(&polynomials.len()) {
+        return invalid(format!(
+            "Code needs from 2 to {} generator polynomials, got {}",
+            MAX_OUTPUTS,
+            polynomials.len()
+        ));
+    }
+    for polynomial in polynomials {
+        if *polynomial == 0 || *polynomial >> constraint_length != 0 {
+            return invalid(format!(
+                "Polynomial {:o} does not fit register of {} bits",
+                polynomial, constraint_length
+            ));
+        }
+    }
+
+    if let Some(pattern) = puncturing {
+        let period = pattern.first().map_or(0, |row| row.len());
+        if pattern.len() != polynomials.len()
+            || !(1..=MAX_PUNCTURING_PERIOD).contains(&period)
+            || pattern.iter().any(|row| row.len() != period)
+            || pattern.iter().flatten().any(|bit| *bit > 1)
+        {
+            return invalid(format!(
+                "Puncturing pattern must have a row of zeros and ones for every polynomial and from 1 to {} columns",
+                MAX_PUNCTURING_PERIOD
+            ));
+        }
+        // every input bit must leave at least one output bit
+        if (0..period).any(|column| pattern.iter().all(|row| row[column] == 0)) {
+            return invalid(String::from(
+                "Puncturing pattern must keep a bit in every column",
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+impl ConvolutionalCode {
+    pub fn new(
+        constraint_length: u8,
+        polynomials: Vec<u16>,
+        puncturing: Option<Vec<Vec<u8>>>,
+    ) -> Result<Self, Error> {
+        check_parameters(constraint_length, &polynomials, puncturing.as_ref())?;
+
+        Ok(Self {
+            constraint_length: constraint_length as usize,
+            polynomials,
+            puncturing,
+        })
+    }
+
+    fn is_sent(&self, step: usize, output: usize) -> bool {
+        match &self.puncturing {
+            Some(pattern) => pattern[output][step % pattern[output].len()] == 1,
+            None => true,
+        }
+    }
+
+    fn outputs(&self, register: usize) -> impl Iterator<Item = u8> + '_ {
+        self.polynomials
+            .iter()
+            .map(move |polynomial| (register & *polynomial as usize).count_ones() as u8 & 1)
+    }
+
+    /// amount of bits sent for `data_length` data bits, tail included
+    pub fn encoded_length(&self, data_length: usize) -> usize {
+        let steps = data_length + self.constraint_length - 1;
+        (0..steps)
+            .map(|step| {
+                (0..self.polynomials.len())
+                    .filter(|output| self.is_sent(step, *output))
+                    .count()
+            })
+            .sum()
+    }
+
+    pub fn encode(&self, data: &[u8], encoded: &mut BitMap) {
+        let tail = vec![0; self.constraint_length - 1];
+        let mut state = 0;
+
+        for (step, bit) in data.iter().chain(&tail).enumerate() {
+            let register = ((*bit as usize) << (self.constraint_length - 1)) | state;
+            for (output, code_bit) in self.outputs(register).enumerate() {
+                if self.is_sent(step, output) {
+                    encoded.add_bit(code_bit);
+                }
+            }
+            state = register >> 1;
+        }
+    }
+
+    /// reads `encoded_length(data_length)` bits and returns the most likely data
+    pub fn decode_hard(
+        &self,
+        bits: &mut impl BitSource,
+        data_length: usize,
+    ) -> Result<Vec<u8>, Error> {
+        let mut ratios = Vec::with_capacity(self.encoded_length(data_length));
+        for _ in 0..ratios.capacity() {
+            ratios.push(match bits.read_bit()? {
+                0 => 1.0,
+                _ => -1.0,
+            });
+        }
+
+        self.decode_soft(&ratios, data_length)
+    }
+
+    /// `ratios` are log(P(0) / P(1)) of every sent bit
+    pub fn decode_soft(&self, ratios: &[f64], data_length: usize) -> Result<Vec<u8>, Error> {
+        if ratios.len() != self.encoded_length(data_length) {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "{} data bits are encoded into {} bits, got {}",
+                    data_length,
+                    self.encoded_length(data_length),
+                    ratios.len()
+                ),
+            ));
+        }
+
+        let state_count = 1 << (self.constraint_length - 1);
+        let steps = data_length + self.constraint_length - 1;
+        let mut metrics = vec![f64::NEG_INFINITY; state_count];
+        metrics[0] = 0.0;
+        // state each state of every step is reached from
+        let mut survivors: Vec<Vec<u16>> = Vec::with_capacity(steps);
+        let mut ratios = ratios.iter();
+
+        for step in 0..steps {
+            // punctured bits carry no information
+            let received: Vec<f64> = (0..self.polynomials.len())
+                .map(|output| match self.is_sent(step, output) {
+                    true => *ratios.next().unwrap(),
+                    false => 0.0,
+                })
+                .collect();
+            let inputs: &[usize] = if step < data_length { &[0, 1] } else { &[0] };
+
+            let mut next_metrics = vec![f64::NEG_INFINITY; state_count];
+            let mut previous = vec![0_u16; state_count];
+            for (state, metric) in metrics.iter().enumerate() {
+                if *metric == f64::NEG_INFINITY {
+                    continue;
+                }
+
+                for input in inputs {
+                    let register = (input << (self.constraint_length - 1)) | state;
+                    let branch: f64 = self
+                        .outputs(register)
+                        .zip(&received)
+                        .map(|(bit, ratio)| if bit == 0 { *ratio } else { -ratio })
+                        .sum();
+
+                    let next = register >> 1;
+                    if metric + branch > next_metrics[next] {
+                        next_metrics[next] = metric + branch;
+                        previous[next] = state as u16;
+                    }
+                }
+            }
+
+            metrics = next_metrics;
+            survivors.push(previous);
+        }
+
+        // tail brings the register back to zero state; input bit is the highest one of a state
+        let mut data = vec![0; steps];
+        let mut state = 0;
+        for step in (0..steps).rev() {
+            data[step] = (state >> (self.constraint_length - 2)) as u8 & 1;
+            state = survivors[step][state] as usize;
+        }
+        data.truncate(data_length);
+
+        Ok(data)
+    }
+}
@@ -0,0 +1,62 @@
+// CRC-32 as used by zip, png and ethernet: reflected polynomial 0xEDB88320,
+// initial value and final xor 0xFFFFFFFF
+
+const POLYNOMIAL: u32 = 0xEDB8_8320;
+const TABLE: [u32; 256] = build_table();
+
+const fn build_table() -> [u32; 256] {
+    let mut table = [0; 256];
+
+    let mut byte = 0;
+    while byte < 256 {
+        let mut crc = byte as u32;
+
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ POLYNOMIAL
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+
+        table[byte] = crc;
+        byte += 1;
+    }
+
+    table
+}
+
+/// incremental CRC-32, data can be fed in any amount of chunks
+pub struct Crc32 {
+    value: u32,
+}
+
+impl Crc32 {
+    pub fn new() -> Self {
+        Self { value: u32::MAX }
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        for byte in data {
+            self.value = TABLE[((self.value ^ *byte as u32) & 0xFF) as usize] ^ (self.value >> 8);
+        }
+    }
+
+    pub fn finalize(&self) -> u32 {
+        self.value ^ u32::MAX
+    }
+}
+
+impl Default for Crc32 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub fn checksum(data: &[u8]) -> u32 {
+    let mut crc = Crc32::new();
+    crc.update(data);
+    crc.finalize()
+}
@@ -1,4 +1,92 @@
-use std::{cmp::min, io::Write};
+use std::{
+    cmp::min,
+    io::{Error, ErrorKind, Write},
+};
+
+// Hamming code: parity bit at position 2^i (positions are counted from the end of block, starting
+// from 1) covers every position with i-th bit set, so positions of failed parity bits (syndrome)
+// add up to the position of a flipped bit. Blocks of 2^r - 1 bits use all r parity bits, shorter
+// blocks are shortened codes. Blocks of 2^r bits are not allowed: their last parity bit covers only
+// itself. Hamming code cannot tell one flipped bit from two, so two errors are "corrected" into three.
+// Extended Hamming code (SECDED) appends parity of the whole block: a single error flips it,
+// a double one keeps it and leaves non-zero syndrome, so such a block is reported instead of
+// being miscorrected.
+
+pub const MIN_CODE_LENGTH: u8 = 7;
+
+/// what check of a block found
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BlockStatus {
+    Valid,
+    Corrected(usize), // index of the flipped bit in the block
+    Uncorrectable,
+}
+
+/// errors found in a package of blocks
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ParityReport {
+    pub blocks_checked: usize,
+    pub corrected: Vec<(usize, usize)>, // index of block and index of flipped bit in it
+    pub uncorrectable: Vec<usize>,      // indices of blocks whose errors were only detected
+}
+
+impl ParityReport {
+    pub fn add(&mut self, status: BlockStatus) {
+        match status {
+            BlockStatus::Valid => {}
+            BlockStatus::Corrected(position) => {
+                self.corrected.push((self.blocks_checked, position))
+            }
+            BlockStatus::Uncorrectable => self.uncorrectable.push(self.blocks_checked),
+        }
+        self.blocks_checked += 1;
+    }
+
+    /// appends report of the following blocks
+    pub fn append(&mut self, other: ParityReport) {
+        let shift = self.blocks_checked;
+        self.corrected.extend(
+            other
+                .corrected
+                .into_iter()
+                .map(|(block, position)| (block + shift, position)),
+        );
+        self.uncorrectable
+            .extend(other.uncorrectable.into_iter().map(|block| block + shift));
+        self.blocks_checked += other.blocks_checked;
+    }
+}
+
+pub fn check_code_length(code_length: u8, extended: bool) -> Result<(), Error> {
+    let min_length = MIN_CODE_LENGTH + extended as u8;
+    if code_length < min_length {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!(
+                "Code length must be in range of {} - 255, got {}",
+                min_length, code_length
+            ),
+        ));
+    }
+
+    let hamming_length = code_length - extended as u8;
+    if hamming_length.is_power_of_two() {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!(
+                "Code length {} is not allowed: Hamming part of block can not be a power of two",
+                code_length
+            ),
+        ));
+    }
+
+    Ok(())
+}
+
+/// amount of data bits in a block of `code_length` bits
+pub fn block_data_length(code_length: usize, extended: bool) -> usize {
+    data_length(code_length - extended as usize)
+}
 
 pub fn add_parity(data: &[u8]) -> Vec<u8> {
     let mut message = data_to_message(data);
@@ -31,11 +119,41 @@ pub fn remove_parity(message: &mut [u8]) -> Vec<u8> {
     let syndrom = check_for_erros(message);
 
     correct_error(message, &syndrom);
-    message_to_data(&message)
+    message_to_data(message)
+}
+
+/// Hamming block followed by parity of all its bits
+pub fn add_extended_parity(data: &[u8]) -> Vec<u8> {
+    let mut message = add_parity(data);
+    message.push(message.iter().fold(0, |parity, bit| parity ^ bit));
+
+    message
+}
+
+/// corrects a single error, double errors are detected and left as they are
+pub fn remove_extended_parity(message: &mut [u8]) -> (Vec<u8>, BlockStatus) {
+    let (hamming_part, overall) = message.split_at_mut(message.len() - 1);
+    let parity = hamming_part
+        .iter()
+        .fold(overall[0], |parity, bit| parity ^ bit);
+    let syndrom = check_for_erros(hamming_part);
+
+    let status = match (parity, syndrom.contains(&1)) {
+        (0, false) => BlockStatus::Valid,
+        (0, true) => BlockStatus::Uncorrectable,
+        // syndrome of hamming part is clean, so the overall parity bit itself is flipped
+        (_, false) => {
+            overall[0] ^= 1;
+            BlockStatus::Corrected(hamming_part.len())
+        }
+        (_, true) => correct_error(hamming_part, &syndrom),
+    };
+
+    (message_to_data(hamming_part), status)
 }
 
 /// assumes that package length is exactly divisible by data_len
-pub fn add_parity_package(package: &[u8], data_len: usize) -> Vec<u8> {
+pub fn add_parity_package(package: &[u8], data_len: usize, extended: bool) -> Vec<u8> {
     let mut encoded = vec![];
     let pckg_len = package.len();
 
@@ -48,27 +166,52 @@ pub fn add_parity_package(package: &[u8], data_len: usize) -> Vec<u8> {
                 .write_all(&vec![0; data_len - data_to_encode.len()])
                 .unwrap();
 
-            encoded.append(&mut add_parity(&data_to_encode));
+            encoded.append(&mut add_block_parity(&data_to_encode, extended));
             continue;
         }
 
-        encoded.append(&mut add_parity(data_to_encode));
+        encoded.append(&mut add_block_parity(data_to_encode, extended));
     }
 
     encoded
 }
 
-pub fn remove_parity_package(package: &mut [u8], message_len: usize) -> Vec<u8> {
+fn add_block_parity(data: &[u8], extended: bool) -> Vec<u8> {
+    match extended {
+        true => add_extended_parity(data),
+        false => add_parity(data),
+    }
+}
+
+/// returns decoded data and errors found in every block of package
+pub fn remove_parity_package(
+    package: &mut [u8],
+    message_len: usize,
+    extended: bool,
+) -> (Vec<u8>, ParityReport) {
     let mut decoded = vec![];
+    let mut report = ParityReport::default();
     let pckg_len = package.len();
 
     for msg_ptr in (0..pckg_len).step_by(message_len) {
         let msg_to_decode = &mut package[msg_ptr..min(msg_ptr + message_len, pckg_len)];
 
-        decoded.append(&mut remove_parity(msg_to_decode));
+        let (mut data, status) = remove_block_parity(msg_to_decode, extended);
+        decoded.append(&mut data);
+        report.add(status);
+    }
+
+    (decoded, report)
+}
+
+fn remove_block_parity(message: &mut [u8], extended: bool) -> (Vec<u8>, BlockStatus) {
+    if extended {
+        return remove_extended_parity(message);
     }
 
-    decoded
+    let syndrom = check_for_erros(message);
+    let status = correct_error(message, &syndrom);
+    (message_to_data(message), status)
 }
 
 fn data_to_message(data: &[u8]) -> Vec<u8> {
@@ -150,26 +293,26 @@ fn check_for_erros(message: &[u8]) -> Vec<u8> {
     syndrom
 }
 
-fn correct_error(message: &mut [u8], syndrom: &Vec<u8>) {
-    if !syndrom.contains(&1) {
-        return;
-    }
-
-    let mut power_of_two = 1;
-    let mut wrong_bit = 0;
-
+fn correct_error(message: &mut [u8], syndrom: &[u8]) -> BlockStatus {
     // converting binary form to decimal
-    for i in 0..syndrom.len() {
-        if syndrom[i] == 1 {
-            wrong_bit += power_of_two;
-        }
-        power_of_two *= 2;
+    let wrong_bit = syndrom
+        .iter()
+        .rev()
+        .fold(0, |position, bit| (position << 1) | *bit as usize);
+
+    if wrong_bit == 0 {
+        return BlockStatus::Valid;
+    }
+    // shortened block has no such position, so there are several errors
+    if wrong_bit > message.len() {
+        return BlockStatus::Uncorrectable;
     }
 
     // reversing index
     let wrong_bit = message.len() - wrong_bit;
 
     message[wrong_bit] = (1 + message[wrong_bit]) % 2;
+    BlockStatus::Corrected(wrong_bit)
 }
 
 pub fn message_length(data_len: usize) -> usize {
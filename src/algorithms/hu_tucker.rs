@@ -0,0 +1,173 @@
+use std::collections::HashMap;
+use std::io::{Error, ErrorKind};
+
+use crate::bit_map::BitSource;
+use crate::types::FileStats;
+
+// Optimal alphabetic code: codes are ordered the same way as symbols, so comparing codes
+// lexicographically gives the same result as comparing original bytes. Code lengths are found with
+// Garsia-Wachs algorithm (it gives the same lengths as Hu-Tucker one):
+// 1. While there are several trees, find the first consecutive weights x, y, z with x <= z, join
+//    trees of x and y and move the joined tree left, right behind the nearest weight not below x + y.
+// 2. Depths of leaves in the final tree are code lengths; the tree itself is not alphabetic.
+// 3. Codes are assigned in symbol order: every next code is previous plus one, extended with zeros
+//    or cut to its length. Lengths of step 1 guarantee only zeros are cut.
+
+enum Tree {
+    Leaf(usize), // index of the symbol in order of bytes
+    Join(usize, usize),
+}
+
+pub fn encode(stats: FileStats) -> HashMap<u8, Vec<u8>> {
+    let symbols: Vec<u8> = (0..=255)
+        .filter(|byte| stats.0[*byte as usize] != 0)
+        .collect();
+
+    // the only symbol still needs at least one bit
+    if symbols.len() < 2 {
+        return symbols.iter().map(|symbol| (*symbol, vec![0])).collect();
+    }
+
+    let weights: Vec<u128> = symbols
+        .iter()
+        .map(|symbol| stats.0[*symbol as usize] as u128)
+        .collect();
+    let lengths = code_lengths(&weights);
+
+    let order: Vec<(u8, usize)> = symbols.into_iter().zip(lengths).collect();
+    alphabetic_codes(&order)
+        .expect("Garsia-Wachs lengths always form an alphabetic code")
+        .into_iter()
+        .collect()
+}
+
+/// code lengths of optimal alphabetic code of symbols with given weights
+fn code_lengths(weights: &[u128]) -> Vec<usize> {
+    let mut trees: Vec<Tree> = (0..weights.len()).map(Tree::Leaf).collect();
+    // working sequence of (weight, tree) with sentinels of infinite weight on both ends
+    let mut sequence: Vec<(u128, usize)> = vec![(u128::MAX, usize::MAX)];
+    sequence.extend(weights.iter().copied().zip(0..));
+    sequence.push((u128::MAX, usize::MAX));
+
+    while sequence.len() > 3 {
+        // the last sentinel makes the search always succeed
+        let first = (1..sequence.len() - 1)
+            .find(|i| sequence[*i].0 <= sequence[*i + 2].0)
+            .unwrap();
+
+        let (x, left) = sequence.remove(first);
+        let (y, right) = sequence.remove(first);
+        trees.push(Tree::Join(left, right));
+        let joined = (x + y, trees.len() - 1);
+
+        // the first sentinel makes this search always succeed
+        let behind = (0..first)
+            .rev()
+            .find(|j| sequence[*j].0 >= joined.0)
+            .unwrap();
+        sequence.insert(behind + 1, joined);
+    }
+
+    let mut lengths = vec![0; weights.len()];
+    let mut stack = vec![(sequence[1].1, 0)];
+    while let Some((tree, depth)) = stack.pop() {
+        match trees[tree] {
+            Tree::Leaf(symbol) => lengths[symbol] = depth,
+            Tree::Join(left, right) => {
+                stack.push((left, depth + 1));
+                stack.push((right, depth + 1));
+            }
+        }
+    }
+
+    lengths
+}
+
+/// codes of symbols that go in increasing order with given lengths;
+/// None when the lengths cannot form an alphabetic prefix code
+fn alphabetic_codes(order: &[(u8, usize)]) -> Option<Vec<(u8, Vec<u8>)>> {
+    let mut codes = Vec::with_capacity(order.len());
+    let mut code: Vec<u8> = vec![];
+
+    for (i, (symbol, length)) in order.iter().enumerate() {
+        if *length == 0 {
+            return None;
+        }
+
+        if i != 0 {
+            // binary increment, an overflow means there is no room left for codes
+            let position = code.iter().rposition(|bit| *bit == 0)?;
+            code.truncate(position);
+            code.push(1);
+        }
+
+        if code.len() > *length && code[*length..].contains(&1) {
+            return None;
+        }
+        code.resize(*length, 0);
+
+        codes.push((*symbol, code.clone()));
+    }
+
+    Some(codes)
+}
+
+/// rebuilds alphabetic code from lengths of codes, as stored in "Dictionary" section
+pub fn assign_codes(lengths: &HashMap<u8, usize>) -> Result<HashMap<u8, Vec<u8>>, Error> {
+    let mut order: Vec<(u8, usize)> = lengths.iter().map(|(s, l)| (*s, *l)).collect();
+    order.sort();
+
+    match alphabetic_codes(&order) {
+        Some(codes) => Ok(codes.into_iter().collect()),
+        None => Err(Error::new(
+            ErrorKind::InvalidData,
+            "Code lengths do not form an alphabetic code",
+        )),
+    }
+}
+
+/// decodes alphabetic code bit by bit walking its binary tree
+pub struct AlphabeticDecoder {
+    children: Vec<[usize; 2]>, // 0 - there is no such child
+    symbols: Vec<Option<u8>>,  // symbol of every leaf
+}
+
+impl AlphabeticDecoder {
+    pub fn new(lengths: &HashMap<u8, usize>) -> Result<Self, Error> {
+        let mut decoder = Self {
+            children: vec![[0, 0]],
+            symbols: vec![None],
+        };
+
+        for (symbol, code) in assign_codes(lengths)? {
+            let mut node = 0;
+            for bit in code {
+                if decoder.children[node][bit as usize] == 0 {
+                    decoder.children.push([0, 0]);
+                    decoder.symbols.push(None);
+                    decoder.children[node][bit as usize] = decoder.children.len() - 1;
+                }
+                node = decoder.children[node][bit as usize];
+            }
+            decoder.symbols[node] = Some(symbol);
+        }
+
+        Ok(decoder)
+    }
+
+    pub fn decode(&self, bits: &mut impl BitSource) -> Result<u8, Error> {
+        let mut node = 0;
+        loop {
+            node = self.children[node][bits.read_bit()? as usize];
+            if node == 0 {
+                return Err(Error::new(
+                    ErrorKind::InvalidData,
+                    "Bit sequence does not match any code of the dictionary",
+                ));
+            }
+            if let Some(symbol) = self.symbols[node] {
+                return Ok(symbol);
+            }
+        }
+    }
+}
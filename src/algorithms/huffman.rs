@@ -1,15 +1,25 @@
-use super::create_quantity_map;
+use super::{canonical, create_quantity_map};
+use crate::bit_map::{BitMap, BitSource};
 use crate::types::{FileStats, Node, Quantity};
 use min_max_heap::MinMaxHeap;
 use std::collections::HashMap;
+use std::io::{Error, ErrorKind};
 
 pub fn encode(stats: FileStats) -> HashMap<u8, Vec<u8>> {
-    let probs = create_quantity_map(&stats.0);
-    let mut min_max_heap: MinMaxHeap<Node<Quantity>> = MinMaxHeap::new();
+    encode_indexed(&stats.0)
+        .into_iter()
+        .map(|(symbol, code)| (symbol as u8, code))
+        .collect()
+}
 
-    for probability in probs {
-        if probability.quantity != 0 {
-            min_max_heap.push(Node::new(probability));
+/// Huffman code for alphabet of any size, symbols are indices of `quantities`.
+/// Absent symbols (of zero quantity) get no code
+pub fn encode_indexed(quantities: &[u64]) -> HashMap<usize, Vec<u8>> {
+    let mut min_max_heap: MinMaxHeap<Node<(u128, usize)>> = MinMaxHeap::new();
+
+    for (symbol, quantity) in quantities.iter().enumerate() {
+        if *quantity != 0 {
+            min_max_heap.push(Node::new((*quantity as u128, symbol)));
         }
     }
 
@@ -17,17 +27,31 @@ pub fn encode(stats: FileStats) -> HashMap<u8, Vec<u8>> {
     enc(min_max_heap)
 }
 
-fn enc(mut min_heap: MinMaxHeap<Node<Quantity>>) -> HashMap<u8, Vec<u8>> {
+/// lengths of `encode_indexed` codes, absent symbols get zero length
+pub fn code_lengths(quantities: &[u64]) -> Vec<usize> {
+    let codes = encode_indexed(quantities);
+    (0..quantities.len())
+        .map(|symbol| codes.get(&symbol).map_or(0, Vec::len))
+        .collect()
+}
+
+/// nodes hold quantity and symbol, ties of quantities are broken by symbol
+fn enc(mut min_heap: MinMaxHeap<Node<(u128, usize)>>) -> HashMap<usize, Vec<u8>> {
     let mut codes = HashMap::new();
 
+    // tree of a single leaf has no edges, still the symbol needs at least one bit
+    if min_heap.len() < 2 {
+        if let Some(leaf) = min_heap.pop_min() {
+            codes.insert(leaf.val.1, vec![0]);
+        }
+        return codes;
+    }
+
     while min_heap.len() > 1 {
         let left_node = min_heap.pop_min().unwrap();
         let right_node = min_heap.pop_min().unwrap();
 
-        let mut new_node = Node::new(Quantity {
-            byte: 0,
-            quantity: left_node.val.quantity + right_node.val.quantity,
-        });
+        let mut new_node = Node::new((left_node.val.0 + right_node.val.0, 0));
 
         new_node.push_left(left_node);
         new_node.push_right(right_node);
@@ -50,12 +74,12 @@ fn enc(mut min_heap: MinMaxHeap<Node<Quantity>>) -> HashMap<u8, Vec<u8>> {
 }
 
 fn walk_tree_with_action(
-    root: &mut Node<Quantity>,
+    root: &mut Node<(u128, usize)>,
     codes_array: &mut Vec<u8>,
-    codes_map: &mut HashMap<u8, Vec<u8>>,
+    codes_map: &mut HashMap<usize, Vec<u8>>,
 ) {
     if root.left.is_none() && root.right.is_none() {
-        codes_map.insert(root.val.byte, codes_array.clone());
+        codes_map.insert(root.val.1, codes_array.clone());
 
         codes_array.pop();
         return;
@@ -72,3 +96,262 @@ fn walk_tree_with_action(
 
     codes_array.pop();
 }
+
+/// builds Huffman code whose codes are not longer than `max_length` bits.
+/// Code lengths are found with package-merge algorithm, codes themselves are canonical
+pub fn encode_limited(stats: FileStats, max_length: usize) -> Result<HashMap<u8, Vec<u8>>, String> {
+    let mut leaves: Vec<Quantity> = create_quantity_map(&stats.0)
+        .into_iter()
+        .filter(|q| q.quantity != 0)
+        .collect();
+    leaves.sort();
+
+    if leaves.len() < 2 {
+        return Ok(leaves.iter().map(|q| (q.byte, vec![0])).collect());
+    }
+
+    let min_length = (usize::BITS - (leaves.len() - 1).leading_zeros()) as usize;
+    if max_length < min_length {
+        return Err(format!(
+            "{} distinct symbols need codes of at least {} bits, limit is {}",
+            leaves.len(),
+            min_length,
+            max_length
+        ));
+    }
+
+    let lengths = package_merge(&leaves, max_length);
+
+    Ok(canonical::assign_codes(
+        &leaves
+            .iter()
+            .zip(lengths)
+            .map(|(q, length)| (q.byte, length))
+            .collect(),
+    ))
+}
+
+/// `leaves` must be sorted by quantity; returns code length of every leaf
+fn package_merge(leaves: &[Quantity], max_length: usize) -> Vec<usize> {
+    // every item is its weight and how many times each leaf is used inside it
+    let leaf_items: Vec<(u128, Vec<usize>)> = leaves
+        .iter()
+        .enumerate()
+        .map(|(i, q)| {
+            let mut usage = vec![0; leaves.len()];
+            usage[i] = 1;
+            (q.quantity, usage)
+        })
+        .collect();
+
+    // start from the deepest level and go up, each level is leaves merged with packages of the level below
+    let mut items = leaf_items.clone();
+    for _ in 1..max_length {
+        let packages = items.chunks_exact(2).map(|pair| {
+            let usage = pair[0]
+                .1
+                .iter()
+                .zip(&pair[1].1)
+                .map(|(a, b)| a + b)
+                .collect();
+            (pair[0].0 + pair[1].0, usage)
+        });
+
+        let mut merged: Vec<(u128, Vec<usize>)> =
+            leaf_items.iter().cloned().chain(packages).collect();
+        merged.sort_by_key(|(weight, _)| *weight); // stable, so leaves go before packages of equal weight
+        items = merged;
+    }
+
+    let mut lengths = vec![0; leaves.len()];
+    for (_, usage) in items.iter().take(2 * leaves.len() - 2) {
+        for (length, used) in lengths.iter_mut().zip(usage) {
+            *length += used;
+        }
+    }
+
+    lengths
+}
+
+pub const MIN_ARITY: u8 = 2;
+pub const MAX_ARITY: u8 = 16;
+pub const DEFAULT_ARITY: u8 = 3;
+
+pub fn check_arity(arity: u8) -> Result<(), Error> {
+    if !(MIN_ARITY..=MAX_ARITY).contains(&arity) {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!(
+                "Code alphabet must have from {} to {} digits, got {}",
+                MIN_ARITY, MAX_ARITY, arity
+            ),
+        ));
+    }
+
+    Ok(())
+}
+
+/// weight of a subtree and symbols of its leaves
+#[derive(PartialEq, Eq, PartialOrd, Ord)]
+struct Subtree {
+    quantity: u128,
+    symbols: Vec<u8>,
+}
+
+/// builds Huffman code over `arity` digits, codes are sequences of digits from 0 to `arity - 1`.
+/// Every merge takes `arity` lightest subtrees, so leaves of zero weight are added beforehand
+/// until the tree gets full: (leaves - 1) must be a multiple of (arity - 1)
+pub fn encode_d_ary(stats: FileStats, arity: u8) -> HashMap<u8, Vec<u8>> {
+    let mut codes: HashMap<u8, Vec<u8>> = HashMap::new();
+    let mut min_max_heap: MinMaxHeap<Subtree> = MinMaxHeap::new();
+
+    for probability in create_quantity_map(&stats.0) {
+        if probability.quantity != 0 {
+            codes.insert(probability.byte, vec![]);
+            min_max_heap.push(Subtree {
+                quantity: probability.quantity,
+                symbols: vec![probability.byte],
+            });
+        }
+    }
+
+    if min_max_heap.len() < 2 {
+        return codes.into_keys().map(|symbol| (symbol, vec![0])).collect();
+    }
+
+    let arity = arity as usize;
+    while !(min_max_heap.len() - 1).is_multiple_of(arity - 1) {
+        min_max_heap.push(Subtree {
+            quantity: 0,
+            symbols: vec![],
+        });
+    }
+
+    while min_max_heap.len() > 1 {
+        let mut merged = Subtree {
+            quantity: 0,
+            symbols: vec![],
+        };
+
+        for digit in 0..arity {
+            let subtree = min_max_heap.pop_min().unwrap();
+            // digits are collected from leaves up, so codes are reversed in the end
+            for symbol in &subtree.symbols {
+                codes.get_mut(symbol).unwrap().push(digit as u8);
+            }
+            merged.quantity += subtree.quantity;
+            merged.symbols.extend(subtree.symbols);
+        }
+
+        min_max_heap.push(merged);
+    }
+
+    for code in codes.values_mut() {
+        code.reverse();
+    }
+    codes
+}
+
+/// widest group of packed digits, so a damaged bit spoils few digits and padding stays short
+pub const MAX_GROUP_BITS: usize = 16;
+
+/// how many digits are packed together and into how many bits. Groups of up to `MAX_GROUP_BITS`
+/// bits are chosen to waste the least bits per digit, e.g. 5 ternary digits take 8 bits instead of 10
+pub fn digit_packing(arity: u8) -> (usize, usize) {
+    let bits_for = |values: u64| (u64::BITS - (values - 1).leading_zeros()) as usize;
+    let mut best = (1, bits_for(arity as u64));
+
+    let mut group_digits = 2;
+    loop {
+        let group_bits = bits_for((arity as u64).pow(group_digits as u32));
+        if group_bits > MAX_GROUP_BITS {
+            break;
+        }
+        // compares group_bits / group_digits with the best ratio so far
+        if group_bits * best.0 < best.1 * group_digits {
+            best = (group_digits, group_bits);
+        }
+        group_digits += 1;
+    }
+
+    best
+}
+
+/// packs digits of D-ary codes into bits by groups of `digit_packing`
+pub struct DigitWriter {
+    arity: u64,
+    group_digits: usize,
+    group_bits: usize,
+    group: u64,
+    length: usize, // digits in the current group
+}
+
+impl DigitWriter {
+    pub fn new(arity: u8) -> Result<Self, Error> {
+        check_arity(arity)?;
+        let (group_digits, group_bits) = digit_packing(arity);
+
+        Ok(Self {
+            arity: arity as u64,
+            group_digits,
+            group_bits,
+            group: 0,
+            length: 0,
+        })
+    }
+
+    pub fn write(&mut self, digits: &[u8], bits: &mut BitMap) {
+        for digit in digits {
+            self.group = self.group * self.arity + *digit as u64;
+            self.length += 1;
+
+            if self.length == self.group_digits {
+                bits.add_value(self.group, self.group_bits);
+                self.group = 0;
+                self.length = 0;
+            }
+        }
+    }
+
+    /// fills the last group with zeros
+    pub fn finish(&mut self, bits: &mut BitMap) {
+        if self.length != 0 {
+            let padding = vec![0; self.group_digits - self.length];
+            self.write(&padding, bits);
+        }
+    }
+}
+
+/// reads digits packed by `DigitWriter`
+pub struct DigitReader {
+    arity: u64,
+    group_digits: usize,
+    group_bits: usize,
+    digits: Vec<u8>, // the rest of current group, the next digit is the last one
+}
+
+impl DigitReader {
+    pub fn new(arity: u8) -> Result<Self, Error> {
+        check_arity(arity)?;
+        let (group_digits, group_bits) = digit_packing(arity);
+
+        Ok(Self {
+            arity: arity as u64,
+            group_digits,
+            group_bits,
+            digits: Vec::with_capacity(group_digits),
+        })
+    }
+
+    pub fn read_digit(&mut self, bits: &mut impl BitSource) -> Result<u8, Error> {
+        if self.digits.is_empty() {
+            let mut group = bits.read_bits(self.group_bits)?;
+            for _ in 0..self.group_digits {
+                self.digits.push((group % self.arity) as u8);
+                group /= self.arity;
+            }
+        }
+
+        Ok(self.digits.pop().unwrap())
+    }
+}
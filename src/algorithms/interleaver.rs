@@ -0,0 +1,102 @@
+use std::collections::VecDeque;
+use std::io::{Error, ErrorKind};
+
+// Interleavers reorder bits of codewords before they are written, so a burst of damaged bits in
+// archive is spread over several codewords and every one of them gets few enough errors to correct.
+// Block interleaver writes `depth` codewords as rows of a table and reads it by columns: bits that
+// are neighbours in archive belong to different codewords as long as the burst is at most `depth`.
+// Convolutional (Forney) interleaver sends bits one by one to `depth` branches in turn, branch i
+// is a queue of i * unit delay bits. Deinterleaver has queues of (depth - 1 - i) * unit delay bits,
+// so every bit is delayed by the same `delay()` bits overall: the output starts with that many
+// filler zeros and the interleaver is flushed with as many zeros. Bits that are neighbours in
+// archive were depth * unit delay - 1 bits apart before interleaving; unlike block interleaver it
+// does not need whole groups of codewords.
+
+pub const MIN_DEPTH: u8 = 2;
+
+pub fn check_depth(depth: u8) -> Result<(), Error> {
+    if depth < MIN_DEPTH {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!(
+                "Interleaver depth must be from {} to 255, got {}",
+                MIN_DEPTH, depth
+            ),
+        ));
+    }
+
+    Ok(())
+}
+
+/// `bits` consist of whole tables of `depth` rows by `row_length` bits, rows are read by columns
+pub fn block_interleave(bits: &[u8], depth: usize, row_length: usize) -> Vec<u8> {
+    let mut interleaved = Vec::with_capacity(bits.len());
+    for table in bits.chunks(depth * row_length) {
+        for column in 0..row_length {
+            for row in 0..depth {
+                interleaved.push(table[row * row_length + column]);
+            }
+        }
+    }
+
+    interleaved
+}
+
+/// inverse of `block_interleave`
+pub fn block_deinterleave(bits: &[u8], depth: usize, row_length: usize) -> Vec<u8> {
+    let mut deinterleaved = vec![0; bits.len()];
+    for (table_index, table) in bits.chunks(depth * row_length).enumerate() {
+        let start = table_index * depth * row_length;
+        for (position, bit) in table.iter().enumerate() {
+            let (column, row) = (position / depth, position % depth);
+            deinterleaved[start + row * row_length + column] = *bit;
+        }
+    }
+
+    deinterleaved
+}
+
+pub struct ConvolutionalInterleaver {
+    branches: Vec<VecDeque<u8>>,
+    branch: usize, // branch the next bit goes to
+}
+
+impl ConvolutionalInterleaver {
+    fn with_delays(delays: impl Iterator<Item = usize>) -> Self {
+        Self {
+            branches: delays.map(|delay| VecDeque::from(vec![0; delay])).collect(),
+            branch: 0,
+        }
+    }
+
+    pub fn interleaver(depth: usize, unit_delay: usize) -> Self {
+        Self::with_delays((0..depth).map(|branch| branch * unit_delay))
+    }
+
+    pub fn deinterleaver(depth: usize, unit_delay: usize) -> Self {
+        Self::with_delays((0..depth).rev().map(|branch| branch * unit_delay))
+    }
+
+    /// unit delay that puts bits which are neighbours in archive into different codewords
+    pub fn unit_delay(depth: usize, codeword_length: usize) -> usize {
+        (codeword_length + 1).div_ceil(depth)
+    }
+
+    /// bits between a bit entering interleaver and leaving deinterleaver
+    pub fn delay(depth: usize, unit_delay: usize) -> usize {
+        (depth - 1) * depth * unit_delay
+    }
+
+    pub fn pass(&mut self, bits: &[u8]) -> Vec<u8> {
+        let depth = self.branches.len();
+        bits.iter()
+            .map(|bit| {
+                let branch = &mut self.branches[self.branch];
+                self.branch = (self.branch + 1) % depth;
+
+                branch.push_back(*bit);
+                branch.pop_front().unwrap()
+            })
+            .collect()
+    }
+}
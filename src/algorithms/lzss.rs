@@ -0,0 +1,236 @@
+use std::io::{Error, ErrorKind};
+
+// LZSS: every position of data is coded either as a literal byte or as a reference to an earlier
+// occurrence of the same bytes inside of the sliding window ("length" bytes, "distance" back).
+// Candidates for a match are found through hash chains: positions are linked by hash of their
+// first MIN_MATCH bytes, so only positions sharing that prefix are compared.
+
+pub const MIN_MATCH: usize = 3;
+pub const MAX_MATCH: usize = MIN_MATCH + u8::MAX as usize; // length is stored as a byte
+pub const MIN_WINDOW_BITS: u8 = 8;
+pub const MAX_WINDOW_BITS: u8 = 16;
+pub const DEFAULT_WINDOW_BITS: u8 = 15;
+
+const HASH_BITS: u32 = 15;
+const MAX_CHAIN: usize = 128; // candidates compared at most for a single position
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Token {
+    Literal(u8),
+    Match { length: usize, distance: usize },
+}
+
+pub fn window_size(window_bits: u8) -> Result<usize, Error> {
+    if !(MIN_WINDOW_BITS..=MAX_WINDOW_BITS).contains(&window_bits) {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!(
+                "Window size must be from 2^{} to 2^{} bytes, got 2^{}",
+                MIN_WINDOW_BITS, MAX_WINDOW_BITS, window_bits
+            ),
+        ));
+    }
+
+    Ok(1 << window_bits)
+}
+
+/// distances are coded as slot (Huffman coded) followed by raw extra bits, as in deflate;
+/// returns slot, count of extra bits and their value
+pub fn distance_slot(distance: usize) -> (u8, usize, usize) {
+    let d = distance - 1;
+    if d < 4 {
+        return (d as u8, 0, 0);
+    }
+
+    let magnitude = d.ilog2() as usize;
+    let slot = 2 * magnitude + ((d >> (magnitude - 1)) & 1);
+    let extra_bits = magnitude - 1;
+
+    (slot as u8, extra_bits, d & ((1 << extra_bits) - 1))
+}
+
+pub fn extra_bits(slot: u8) -> usize {
+    if slot < 4 {
+        0
+    } else {
+        slot as usize / 2 - 1
+    }
+}
+
+pub fn distance_from_slot(slot: u8, extra: usize) -> usize {
+    if slot < 4 {
+        return slot as usize + 1;
+    }
+
+    let extra_bits = extra_bits(slot);
+    ((2 | (slot as usize & 1)) << extra_bits) + extra + 1
+}
+
+/// splits data into tokens; data may come in chunks of any size
+pub struct LzssEncoder {
+    window_size: usize,
+    data: Vec<u8>,    // bytes starting at absolute position `base`
+    base: usize,      // bytes before it can no longer be referenced
+    position: usize,  // absolute position of the next byte to code
+    inserted: usize,  // positions before it are linked into hash chains
+    head: Vec<usize>, // hash -> latest position with that hash + 1 (0 - none)
+    prev: Vec<usize>, // position % window_size -> previous position with the same hash + 1
+}
+
+impl LzssEncoder {
+    pub fn new(window_bits: u8) -> Result<Self, Error> {
+        let window_size = window_size(window_bits)?;
+
+        Ok(Self {
+            window_size,
+            data: Vec::new(),
+            base: 0,
+            position: 0,
+            inserted: 0,
+            head: vec![0; 1 << HASH_BITS],
+            prev: vec![0; window_size],
+        })
+    }
+
+    /// passes tokens of all bytes that have enough lookahead (all bytes on end of data) to `emit`
+    pub fn encode<F>(&mut self, buf: &[u8], end_of_data: bool, mut emit: F)
+    where
+        F: FnMut(Token),
+    {
+        self.data.extend_from_slice(buf);
+        let end = self.base + self.data.len();
+        let limit = if end_of_data {
+            end
+        } else {
+            end.saturating_sub(MAX_MATCH)
+        };
+
+        while self.position < limit {
+            let token = self.longest_match(end);
+            self.position += match token {
+                Token::Literal(_) => 1,
+                Token::Match { length, .. } => length,
+            };
+
+            while self.inserted < self.position && self.inserted + MIN_MATCH <= end {
+                self.insert(self.inserted);
+                self.inserted += 1;
+            }
+
+            emit(token);
+        }
+
+        // bytes are dropped in large portions to avoid moving the data on every chunk
+        let keep_from = self.position.saturating_sub(self.window_size);
+        if keep_from - self.base > self.window_size {
+            self.data.drain(..keep_from - self.base);
+            self.base = keep_from;
+        }
+    }
+
+    fn longest_match(&self, end: usize) -> Token {
+        let current = &self.data[self.position - self.base..end - self.base];
+        let max_length = current.len().min(MAX_MATCH);
+        if max_length < MIN_MATCH {
+            return Token::Literal(current[0]);
+        }
+
+        let (mut best_length, mut best_distance) = (0, 0);
+        let mut candidate = self.head[self.hash(self.position)];
+        let mut compared = 0;
+
+        while candidate != 0 && compared < MAX_CHAIN {
+            let start = candidate - 1;
+            let distance = self.position - start;
+            if distance > self.window_size {
+                break;
+            }
+
+            let length = self.data[start - self.base..]
+                .iter()
+                .zip(&current[..max_length])
+                .take_while(|(a, b)| a == b)
+                .count();
+            if length > best_length {
+                (best_length, best_distance) = (length, distance);
+                if length == max_length {
+                    break;
+                }
+            }
+
+            candidate = self.prev[start % self.window_size];
+            compared += 1;
+        }
+
+        if best_length >= MIN_MATCH {
+            Token::Match {
+                length: best_length,
+                distance: best_distance,
+            }
+        } else {
+            Token::Literal(current[0])
+        }
+    }
+
+    fn insert(&mut self, position: usize) {
+        let hash = self.hash(position);
+        self.prev[position % self.window_size] = self.head[hash];
+        self.head[hash] = position + 1;
+    }
+
+    fn hash(&self, position: usize) -> usize {
+        let bytes = &self.data[position - self.base..position - self.base + MIN_MATCH];
+        let prefix = (bytes[0] as u32) << 16 | (bytes[1] as u32) << 8 | bytes[2] as u32;
+
+        (prefix.wrapping_mul(2_654_435_761) >> (32 - HASH_BITS)) as usize
+    }
+}
+
+/// rebuilds data from tokens, keeping the window of already decoded bytes
+pub struct LzssDecoder {
+    window_size: usize,
+    history: Vec<u8>,
+}
+
+impl LzssDecoder {
+    pub fn new(window_bits: u8) -> Result<Self, Error> {
+        let window_size = window_size(window_bits)?;
+
+        Ok(Self {
+            window_size,
+            history: Vec::with_capacity(2 * window_size),
+        })
+    }
+
+    /// appends bytes of the token to `decoded`
+    pub fn decode(&mut self, token: Token, decoded: &mut Vec<u8>) -> Result<(), Error> {
+        let length = match token {
+            Token::Literal(byte) => {
+                self.history.push(byte);
+                1
+            }
+            Token::Match { length, distance } => {
+                if distance == 0 || distance > self.window_size || distance > self.history.len() {
+                    return Err(Error::new(
+                        ErrorKind::InvalidData,
+                        format!("Match distance {} points outside of the window", distance),
+                    ));
+                }
+
+                // source and copy may overlap, so bytes are copied one by one
+                let start = self.history.len() - distance;
+                for i in 0..length {
+                    self.history.push(self.history[start + i]);
+                }
+                length
+            }
+        };
+        decoded.extend_from_slice(&self.history[self.history.len() - length..]);
+
+        if self.history.len() > 2 * self.window_size {
+            self.history.drain(..self.history.len() - self.window_size);
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,196 @@
+use crate::bit_map::{BitMap, BitSource};
+use std::collections::HashMap;
+use std::io::{Error, ErrorKind};
+
+// Lempel-Ziv-Welch coding. Dictionary starts with all single bytes; encoder emits code of the
+// longest string already in dictionary and adds that string extended by the next byte. Decoder
+// rebuilds the same dictionary one step later, so nothing but codes is stored.
+// Codes are written with as many bits as the largest code that may appear (from 9 bits up to
+// `max_bits`); when dictionary gets full, CLEAR_CODE is emitted and both sides start over.
+
+pub const MIN_CODE_BITS: u8 = 9;
+pub const MAX_CODE_BITS: u8 = 16;
+pub const DEFAULT_CODE_BITS: u8 = 12;
+
+const CLEAR_CODE: usize = 256;
+const FIRST_CODE: usize = 257; // first code of multi-byte strings
+
+pub fn check_max_bits(max_bits: u8) -> Result<(), Error> {
+    if !(MIN_CODE_BITS..=MAX_CODE_BITS).contains(&max_bits) {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!(
+                "Maximal code width must be from {} to {} bits, got {}",
+                MIN_CODE_BITS, MAX_CODE_BITS, max_bits
+            ),
+        ));
+    }
+
+    Ok(())
+}
+
+/// width that fits every code below `next_code`
+fn code_width(next_code: usize) -> usize {
+    (usize::BITS - (next_code - 1).leading_zeros()) as usize
+}
+
+pub struct LzwEncoder {
+    max_bits: u8,
+    dictionary: HashMap<(u16, u8), u16>, // (code of string, next byte) -> code of extended string
+    next_code: usize,
+    current: Option<u16>, // code of the longest string matched so far
+}
+
+impl LzwEncoder {
+    pub fn new(max_bits: u8) -> Result<Self, Error> {
+        check_max_bits(max_bits)?;
+
+        Ok(Self {
+            max_bits,
+            dictionary: HashMap::new(),
+            next_code: FIRST_CODE,
+            current: None,
+        })
+    }
+
+    pub fn encode(&mut self, buf: &[u8], bits: &mut BitMap) {
+        for byte in buf {
+            let current = match self.current {
+                Some(current) => current,
+                None => {
+                    self.current = Some(*byte as u16);
+                    continue;
+                }
+            };
+
+            if let Some(code) = self.dictionary.get(&(current, *byte)) {
+                self.current = Some(*code);
+                continue;
+            }
+
+            bits.add_value(current as u64, code_width(self.next_code));
+            self.dictionary
+                .insert((current, *byte), self.next_code as u16);
+            self.next_code += 1;
+
+            if self.next_code == 1 << self.max_bits {
+                bits.add_value(CLEAR_CODE as u64, code_width(self.next_code));
+                self.dictionary.clear();
+                self.next_code = FIRST_CODE;
+            }
+
+            self.current = Some(*byte as u16);
+        }
+    }
+
+    /// emits code of the last matched string
+    pub fn finish(&mut self, bits: &mut BitMap) {
+        if let Some(current) = self.current.take() {
+            bits.add_value(current as u64, code_width(self.next_code));
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Entry {
+    prefix: u16,
+    last: u8,
+    first: u8,
+}
+
+pub struct LzwDecoder {
+    max_bits: u8,
+    entries: Vec<Entry>, // strings with codes from FIRST_CODE
+    previous: Option<usize>,
+    string: Vec<u8>, // buffer for bytes of a string, in the reverse order
+}
+
+impl LzwDecoder {
+    pub fn new(max_bits: u8) -> Result<Self, Error> {
+        check_max_bits(max_bits)?;
+
+        Ok(Self {
+            max_bits,
+            entries: Vec::with_capacity((1 << max_bits) - FIRST_CODE),
+            previous: None,
+            string: Vec::new(),
+        })
+    }
+
+    /// reads one code and appends its string to `decoded`
+    pub fn decode(
+        &mut self,
+        bits: &mut impl BitSource,
+        decoded: &mut Vec<u8>,
+    ) -> Result<(), Error> {
+        let code = loop {
+            // encoder has one more string in dictionary as it adds them a step earlier
+            let next_code = self.next_code() + self.previous.is_some() as usize;
+
+            let code = bits.read_bits(code_width(next_code))? as usize;
+            if code != CLEAR_CODE {
+                break code;
+            }
+
+            self.entries.clear();
+            self.previous = None;
+        };
+
+        if let Some(previous) = self.previous {
+            // code may refer to the string that is being added right now
+            let first = match code {
+                _ if code < self.next_code() => self.first_byte(code),
+                _ if code == self.next_code() => self.first_byte(previous),
+                _ => return Err(unknown_code(code)),
+            };
+
+            // encoder resets dictionary before it gets full
+            if self.next_code() + 1 >= 1 << self.max_bits {
+                return Err(Error::new(
+                    ErrorKind::InvalidData,
+                    "LZW dictionary overflowed without reset",
+                ));
+            }
+
+            self.entries.push(Entry {
+                prefix: previous as u16,
+                last: first,
+                first: self.first_byte(previous),
+            });
+        } else if code >= CLEAR_CODE {
+            return Err(unknown_code(code));
+        }
+
+        let mut current = code;
+        while current >= FIRST_CODE {
+            let entry = self.entries[current - FIRST_CODE];
+            self.string.push(entry.last);
+            current = entry.prefix as usize;
+        }
+        self.string.push(current as u8);
+
+        decoded.extend(self.string.drain(..).rev());
+        self.previous = Some(code);
+
+        Ok(())
+    }
+
+    fn next_code(&self) -> usize {
+        FIRST_CODE + self.entries.len()
+    }
+
+    fn first_byte(&self, code: usize) -> u8 {
+        if code < CLEAR_CODE {
+            code as u8
+        } else {
+            self.entries[code - FIRST_CODE].first
+        }
+    }
+}
+
+fn unknown_code(code: usize) -> Error {
+    Error::new(
+        ErrorKind::InvalidData,
+        format!("Code {} is not in LZW dictionary", code),
+    )
+}
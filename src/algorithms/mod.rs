@@ -1,8 +1,29 @@
 use crate::types::{Probability, Quantity};
 
+pub mod adaptive_huffman;
+pub mod ans;
+pub mod arithmetic;
+pub mod bch;
+pub mod bwt;
+pub mod canonical;
+pub mod context;
+pub mod convolutional;
+pub mod crc32;
 pub mod hamming;
+pub mod hu_tucker;
 pub mod huffman;
+pub mod interleaver;
+pub mod lzss;
+pub mod lzw;
+pub mod ngram;
+pub mod ppm;
+pub mod reed_solomon;
+pub mod shannon;
 pub mod shannon_fano;
+pub mod shannon_fano_elias;
+pub mod tunstall;
+pub mod universal;
+pub mod words;
 
 fn create_probability_map(alphabet: &[u64; 256], total_count: u64) -> Vec<Probability> {
     let mut probabilities = vec![];
@@ -30,3 +51,31 @@ fn create_quantity_map(alphabet: &[u64; 256]) -> Vec<Quantity> {
 
     probs
 }
+
+/// ceil(-log2 p) for p = quantity / total, computed without rounding errors
+fn information_length(quantity: u64, total: u64) -> usize {
+    let mut length = 0;
+    while (quantity as u128) << length < total as u128 {
+        length += 1;
+    }
+
+    length
+}
+
+/// first `length` binary digits of fraction numerator / denominator (which is less than 1)
+fn binary_expansion(numerator: u128, denominator: u128, length: usize) -> Vec<u8> {
+    let mut remainder = numerator;
+    let mut digits = Vec::with_capacity(length);
+
+    for _ in 0..length {
+        remainder *= 2;
+        if remainder >= denominator {
+            digits.push(1);
+            remainder -= denominator;
+        } else {
+            digits.push(0);
+        }
+    }
+
+    digits
+}
@@ -0,0 +1,70 @@
+use std::io::{Error, ErrorKind};
+
+// Source extension: the file is cut into blocks of `length` bytes (n-grams) that are coded as
+// symbols of an alphabet of 256^length letters. Coding longer blocks brings average code length
+// per byte closer to entropy. An n-gram is kept as an integer whose highest byte is the first one;
+// bytes at the end of file that do not fill a whole n-gram make the tail.
+
+pub const MIN_LENGTH: u8 = 2;
+pub const MAX_LENGTH: u8 = 3;
+pub const DEFAULT_LENGTH: u8 = 2;
+
+pub fn check_length(length: u8) -> Result<(), Error> {
+    if !(MIN_LENGTH..=MAX_LENGTH).contains(&length) {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!(
+                "N-gram must be from {} to {} bytes long, got {}",
+                MIN_LENGTH, MAX_LENGTH, length
+            ),
+        ));
+    }
+
+    Ok(())
+}
+
+/// cuts chunks of data into n-grams, n-gram may start in one chunk and end in the next one
+pub struct Ngrams {
+    length: usize,
+    pending: Vec<u8>, // bytes of incomplete n-gram
+}
+
+impl Ngrams {
+    pub fn new(length: u8) -> Self {
+        Self {
+            length: length as usize,
+            pending: Vec::with_capacity(length as usize),
+        }
+    }
+
+    pub fn push<F>(&mut self, buf: &[u8], mut on_ngram: F)
+    where
+        F: FnMut(u32),
+    {
+        for byte in buf {
+            self.pending.push(*byte);
+            if self.pending.len() == self.length {
+                on_ngram(join(&self.pending));
+                self.pending.clear();
+            }
+        }
+    }
+
+    /// bytes left after the last complete n-gram
+    pub fn tail(&self) -> &[u8] {
+        &self.pending
+    }
+}
+
+pub fn join(bytes: &[u8]) -> u32 {
+    bytes
+        .iter()
+        .fold(0, |ngram, byte| (ngram << 8) | *byte as u32)
+}
+
+pub fn split(ngram: u32, length: u8) -> Vec<u8> {
+    (0..length)
+        .rev()
+        .map(|i| (ngram >> (8 * i as u32)) as u8)
+        .collect()
+}
@@ -0,0 +1,203 @@
+use super::arithmetic::{ArithmeticDecoder, ArithmeticEncoder};
+use crate::bit_map::{BitMap, BitSource};
+use std::collections::HashMap;
+use std::io::{Error, ErrorKind};
+
+// Prediction by partial matching (PPMC variant). A byte is predicted by counts of bytes seen after
+// the same preceding `order` bytes, starting from the longest context. When the context has never
+// seen the byte, an escape is coded (its count is the number of distinct bytes of the context) and
+// the next shorter context is tried; bytes of the escaped context are excluded from shorter ones,
+// as they are known not to be the coded byte. Context of order -1 holds all 256 bytes equally.
+// Probabilities are turned into bits by the arithmetic coder.
+
+pub const MIN_ORDER: u8 = 1;
+pub const MAX_ORDER: u8 = 6;
+pub const DEFAULT_ORDER: u8 = 3;
+
+const MAX_CONTEXT_TOTAL: u32 = 1 << 16; // counts of a context are halved when they sum over it
+
+/// cumulative range [low, high) of a coded event out of total
+type Event = (u32, u32, u32);
+
+pub fn check_order(order: u8) -> Result<(), Error> {
+    if !(MIN_ORDER..=MAX_ORDER).contains(&order) {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!(
+                "PPM order must be from {} to {}, got {}",
+                MIN_ORDER, MAX_ORDER, order
+            ),
+        ));
+    }
+
+    Ok(())
+}
+
+pub struct PpmModel {
+    max_order: usize,
+    contexts: HashMap<(u8, u64), Vec<(u8, u32)>>, // (order, preceding bytes) -> counts of bytes
+    history: u64,                                 // the latest bytes, the last one is the lowest
+    seen: usize,                                  // bytes in history, at most `max_order`
+}
+
+impl PpmModel {
+    pub fn new(max_order: u8) -> Result<Self, Error> {
+        check_order(max_order)?;
+
+        Ok(Self {
+            max_order: max_order as usize,
+            contexts: HashMap::new(),
+            history: 0,
+            seen: 0,
+        })
+    }
+
+    pub fn encode(&mut self, byte: u8, encoder: &mut ArithmeticEncoder, bits: &mut BitMap) {
+        for (low, high, total) in self.events(byte) {
+            encoder.encode(low, high, total, bits);
+        }
+        self.update(byte);
+    }
+
+    pub fn decode(
+        &mut self,
+        decoder: &mut ArithmeticDecoder,
+        bits: &mut impl BitSource,
+    ) -> Result<u8, Error> {
+        let mut excluded = [false; 256];
+
+        for order in (0..=self.seen).rev() {
+            let symbols = match self.contexts.get(&self.key(order)) {
+                Some(symbols) => active(symbols, &excluded),
+                None => continue,
+            };
+            if symbols.is_empty() {
+                continue;
+            }
+
+            let escape_low: u32 = symbols.iter().map(|(_, count)| count).sum();
+            let total = escape_low + symbols.len() as u32;
+            let target = decoder.target(total);
+
+            if target >= escape_low {
+                decoder.consume(escape_low, total, total, bits)?;
+                for (symbol, _) in symbols {
+                    excluded[symbol as usize] = true;
+                }
+                continue;
+            }
+
+            let mut low = 0;
+            for (symbol, count) in symbols {
+                if target < low + count {
+                    decoder.consume(low, low + count, total, bits)?;
+                    self.update(symbol);
+                    return Ok(symbol);
+                }
+                low += count;
+            }
+        }
+
+        let remaining: Vec<u8> = (0..=255).filter(|s| !excluded[*s as usize]).collect();
+        if remaining.is_empty() {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                "PPM escaped from every context",
+            ));
+        }
+
+        let total = remaining.len() as u32;
+        let target = decoder.target(total);
+        decoder.consume(target, target + 1, total, bits)?;
+
+        let symbol = remaining[target as usize];
+        self.update(symbol);
+        Ok(symbol)
+    }
+
+    /// ideal length of code of the bytes in bits (arithmetic coder gets very close to it)
+    pub fn code_length(&mut self, buf: &[u8]) -> f64 {
+        let mut length = 0.0;
+        for byte in buf {
+            for (low, high, total) in self.events(*byte) {
+                length += (total as f64 / (high - low) as f64).log2();
+            }
+            self.update(*byte);
+        }
+
+        length
+    }
+
+    /// escapes down to the context that knows the byte and the byte itself
+    fn events(&self, byte: u8) -> Vec<Event> {
+        let mut events = vec![];
+        let mut excluded = [false; 256];
+
+        for order in (0..=self.seen).rev() {
+            let symbols = match self.contexts.get(&self.key(order)) {
+                Some(symbols) => active(symbols, &excluded),
+                None => continue,
+            };
+            if symbols.is_empty() {
+                continue;
+            }
+
+            let escape_low: u32 = symbols.iter().map(|(_, count)| count).sum();
+            let total = escape_low + symbols.len() as u32;
+
+            let mut low = 0;
+            for (symbol, count) in &symbols {
+                if *symbol == byte {
+                    events.push((low, low + count, total));
+                    return events;
+                }
+                low += count;
+            }
+
+            events.push((escape_low, total, total));
+            for (symbol, _) in symbols {
+                excluded[symbol as usize] = true;
+            }
+        }
+
+        let below = (0..byte).filter(|s| !excluded[*s as usize]).count() as u32;
+        let total = excluded.iter().filter(|e| !**e).count() as u32;
+        events.push((below, below + 1, total));
+
+        events
+    }
+
+    fn update(&mut self, byte: u8) {
+        for order in 0..=self.seen {
+            let symbols = self.contexts.entry(self.key(order)).or_default();
+
+            match symbols.iter_mut().find(|(symbol, _)| *symbol == byte) {
+                Some((_, count)) => *count += 1,
+                None => symbols.push((byte, 1)),
+            }
+
+            if symbols.iter().map(|(_, count)| count).sum::<u32>() > MAX_CONTEXT_TOTAL {
+                for (_, count) in symbols.iter_mut() {
+                    *count = (*count / 2).max(1);
+                }
+            }
+        }
+
+        self.history = (self.history << 8) | byte as u64;
+        self.seen = (self.seen + 1).min(self.max_order);
+    }
+
+    fn key(&self, order: usize) -> (u8, u64) {
+        let mask = (1_u64 << (8 * order)) - 1;
+        (order as u8, self.history & mask)
+    }
+}
+
+/// symbols of context that are not excluded, in the order of the context
+fn active(symbols: &[(u8, u32)], excluded: &[bool; 256]) -> Vec<(u8, u32)> {
+    symbols
+        .iter()
+        .filter(|(symbol, _)| !excluded[*symbol as usize])
+        .copied()
+        .collect()
+}
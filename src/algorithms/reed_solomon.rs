@@ -0,0 +1,311 @@
+use super::hamming::ParityReport;
+use std::io::{Error, ErrorKind};
+
+// Reed-Solomon code over GF(2^8): a block of n bytes holds k data bytes followed by n - k parity
+// bytes, so it corrects up to (n - k) / 2 damaged bytes wherever they are and however many bits of
+// them are flipped, which is what burst errors do. Erasures (bytes known to be damaged) take one
+// parity byte each instead of two.
+// Field elements are bytes, multiplication is done with logarithms of primitive element 2 modulo
+// polynomial x^8 + x^4 + x^3 + x^2 + 1. Byte of block at index i is a coefficient of x^(n - 1 - i);
+// codewords are multiples of generator polynomial (x - a^0)(x - a^1)...(x - a^(n - k - 1)).
+// Decoding: syndromes (codeword values at roots of generator) -> error locator with
+// Berlekamp-Massey (started from erasure locator) -> positions of errors with Chien search
+// (roots of locator) -> values of errors with Forney formula.
+
+pub const DEFAULT_BLOCK_LENGTH: u8 = 255;
+pub const DEFAULT_DATA_LENGTH: u8 = 223;
+
+const PRIMITIVE_POLYNOMIAL: u16 = 0x11d;
+
+pub fn check_parameters(n: u8, k: u8) -> Result<(), Error> {
+    if k == 0 || k >= n {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!(
+                "Data length must be from 1 to block length minus one, got n = {}, k = {}",
+                n, k
+            ),
+        ));
+    }
+
+    Ok(())
+}
+
+pub struct ReedSolomon {
+    n: usize,
+    k: usize,
+    exp: [u8; 512], // powers of primitive element, doubled to skip reduction modulo 255
+    log: [usize; 256],
+    generator: Vec<u8>, // coefficients from the highest power
+}
+
+impl ReedSolomon {
+    pub fn new(n: u8, k: u8) -> Result<Self, Error> {
+        check_parameters(n, k)?;
+
+        let mut exp = [0_u8; 512];
+        let mut log = [0_usize; 256];
+        let mut value: u16 = 1;
+        for (power, element) in exp.iter_mut().enumerate().take(255) {
+            *element = value as u8;
+            log[value as usize] = power;
+            value <<= 1;
+            if value & 0x100 != 0 {
+                value ^= PRIMITIVE_POLYNOMIAL;
+            }
+        }
+        for power in 255..512 {
+            exp[power] = exp[power - 255];
+        }
+
+        let mut code = Self {
+            n: n as usize,
+            k: k as usize,
+            exp,
+            log,
+            generator: vec![1],
+        };
+        for power in 0..code.n - code.k {
+            code.generator = code.multiply_polynomials(&code.generator, &[1, code.exp[power]]);
+        }
+
+        Ok(code)
+    }
+
+    pub fn block_length(&self) -> usize {
+        self.n
+    }
+
+    pub fn data_length(&self) -> usize {
+        self.k
+    }
+
+    fn multiply(&self, a: u8, b: u8) -> u8 {
+        match (a, b) {
+            (0, _) | (_, 0) => 0,
+            _ => self.exp[self.log[a as usize] + self.log[b as usize]],
+        }
+    }
+
+    fn divide(&self, a: u8, b: u8) -> u8 {
+        assert_ne!(b, 0, "division by zero in GF(2^8)");
+        match a {
+            0 => 0,
+            _ => self.exp[self.log[a as usize] + 255 - self.log[b as usize]],
+        }
+    }
+
+    /// primitive element raised to `power`, which may be negative
+    fn power(&self, power: isize) -> u8 {
+        self.exp[power.rem_euclid(255) as usize]
+    }
+
+    /// both polynomials and the result go from the same end
+    fn multiply_polynomials(&self, p: &[u8], q: &[u8]) -> Vec<u8> {
+        let mut product = vec![0; p.len() + q.len() - 1];
+        for (i, a) in p.iter().enumerate() {
+            for (j, b) in q.iter().enumerate() {
+                product[i + j] ^= self.multiply(*a, *b);
+            }
+        }
+
+        product
+    }
+
+    /// value of polynomial whose coefficients go from the lowest power
+    fn evaluate(&self, polynomial: &[u8], x: u8) -> u8 {
+        polynomial.iter().rev().fold(0, |value, coefficient| {
+            self.multiply(value, x) ^ coefficient
+        })
+    }
+
+    /// `data` must be `k` bytes long, returns block of `n` bytes that starts with the data
+    pub fn encode(&self, data: &[u8]) -> Vec<u8> {
+        assert_eq!(
+            data.len(),
+            self.k,
+            "Reed-Solomon block takes exactly k bytes"
+        );
+
+        // remainder of data * x^(n - k) divided by generator
+        let mut remainder = data.to_vec();
+        remainder.resize(self.n, 0);
+        for i in 0..self.k {
+            let coefficient = remainder[i];
+            if coefficient != 0 {
+                for (j, g) in self.generator.iter().enumerate().skip(1) {
+                    remainder[i + j] ^= self.multiply(*g, coefficient);
+                }
+            }
+        }
+
+        let mut block = data.to_vec();
+        block.extend_from_slice(&remainder[self.k..]);
+        block
+    }
+
+    /// corrects the block in place; `erasures` are indices of bytes known to be damaged.
+    /// Returns indices of corrected bytes
+    pub fn decode(&self, block: &mut [u8], erasures: &[usize]) -> Result<Vec<usize>, Error> {
+        let parity_length = self.n - self.k;
+        if block.len() != self.n {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("Reed-Solomon block must be {} bytes long", self.n),
+            ));
+        }
+        if erasures.len() > parity_length || erasures.iter().any(|index| *index >= self.n) {
+            return Err(too_many_errors());
+        }
+
+        let syndromes: Vec<u8> = (0..parity_length)
+            .map(|i| {
+                let x = self.exp[i];
+                block
+                    .iter()
+                    .fold(0, |value, byte| self.multiply(value, x) ^ byte)
+            })
+            .collect();
+        if erasures.is_empty() && syndromes.iter().all(|s| *s == 0) {
+            return Ok(vec![]);
+        }
+
+        // locator of a byte at index i is a^(n - 1 - i); polynomials below go from the lowest power
+        let locator_power = |index: usize| (self.n - 1 - index) as isize;
+        let mut erasure_locator = vec![1];
+        for index in erasures {
+            erasure_locator = self
+                .multiply_polynomials(&erasure_locator, &[1, self.power(locator_power(*index))]);
+        }
+
+        let locator = self.berlekamp_massey(&syndromes, erasure_locator, erasures.len())?;
+
+        // Chien search: roots of locator are inverse locators of damaged bytes
+        let positions: Vec<usize> = (0..self.n)
+            .filter(|index| self.evaluate(&locator, self.power(-locator_power(*index))) == 0)
+            .collect();
+        if positions.len() != locator.len() - 1 {
+            return Err(too_many_errors());
+        }
+
+        // Forney formula: value = X * evaluator(1 / X) / locator'(1 / X)
+        let mut evaluator = self.multiply_polynomials(&syndromes, &locator);
+        evaluator.truncate(parity_length);
+        let derivative: Vec<u8> = locator
+            .iter()
+            .enumerate()
+            .skip(1)
+            .map(|(power, c)| if power % 2 == 1 { *c } else { 0 })
+            .collect();
+
+        let mut values = vec![];
+        for index in positions {
+            let x_inverse = self.power(-locator_power(index));
+            let denominator = self.evaluate(&derivative, x_inverse);
+            if denominator == 0 {
+                return Err(too_many_errors());
+            }
+
+            let value = self.multiply(
+                self.power(locator_power(index)),
+                self.divide(self.evaluate(&evaluator, x_inverse), denominator),
+            );
+            if value != 0 {
+                values.push((index, value));
+            }
+        }
+
+        // block is changed only when the whole correction is found
+        for (index, value) in &values {
+            block[*index] ^= value;
+        }
+        Ok(values.into_iter().map(|(index, _)| index).collect())
+    }
+
+    /// locator of errors and erasures; `erasures` is the amount of roots of `erasure_locator`
+    fn berlekamp_massey(
+        &self,
+        syndromes: &[u8],
+        erasure_locator: Vec<u8>,
+        erasures: usize,
+    ) -> Result<Vec<u8>, Error> {
+        let mut locator = erasure_locator.clone();
+        let mut previous = erasure_locator;
+        let mut length = erasures;
+
+        for step in erasures..syndromes.len() {
+            let discrepancy = locator
+                .iter()
+                .enumerate()
+                .take(step + 1)
+                .fold(0, |d, (i, c)| d ^ self.multiply(*c, syndromes[step - i]));
+
+            previous.insert(0, 0);
+            if discrepancy == 0 {
+                continue;
+            }
+
+            let mut next = locator.clone();
+            next.resize(next.len().max(previous.len()), 0);
+            for (i, c) in previous.iter().enumerate() {
+                next[i] ^= self.multiply(discrepancy, *c);
+            }
+
+            if 2 * length <= step + erasures {
+                length = step + 1 + erasures - length;
+                previous = locator
+                    .iter()
+                    .map(|c| self.divide(*c, discrepancy))
+                    .collect();
+            }
+            locator = next;
+        }
+
+        while locator.len() > 1 && locator[locator.len() - 1] == 0 {
+            locator.pop();
+        }
+        if locator.len() - 1 != length || 2 * length - erasures > syndromes.len() {
+            return Err(too_many_errors());
+        }
+
+        Ok(locator)
+    }
+
+    /// splits package into blocks of `k` bytes, the last one is padded with zeros
+    pub fn encode_package(&self, package: &[u8]) -> Vec<u8> {
+        let mut encoded = vec![];
+        for data in package.chunks(self.k) {
+            let mut data = data.to_vec();
+            data.resize(self.k, 0);
+            encoded.append(&mut self.encode(&data));
+        }
+
+        encoded
+    }
+
+    /// package must consist of whole blocks; data of blocks that can not be corrected is left as is
+    pub fn decode_package(&self, package: &mut [u8]) -> (Vec<u8>, ParityReport) {
+        let mut decoded = vec![];
+        let mut report = ParityReport::default();
+
+        for (block_index, block) in package.chunks_mut(self.n).enumerate() {
+            match self.decode(block, &[]) {
+                Ok(positions) => report
+                    .corrected
+                    .extend(positions.into_iter().map(|index| (block_index, index))),
+                Err(_) => report.uncorrectable.push(block_index),
+            }
+            report.blocks_checked += 1;
+            decoded.extend_from_slice(&block[..self.k]);
+        }
+
+        (decoded, report)
+    }
+}
+
+fn too_many_errors() -> Error {
+    Error::new(
+        ErrorKind::InvalidData,
+        "Reed-Solomon block has more errors than it can correct",
+    )
+}
@@ -0,0 +1,31 @@
+use std::collections::HashMap;
+
+use crate::types::FileStats;
+
+use super::{binary_expansion, create_probability_map, information_length};
+
+// Shannon code: symbols are sorted by decreasing probability, symbol with probability p gets
+// ceil(-log2 p) first binary digits of the cumulative probability of all symbols before it.
+// Digits are taken from exact counts, so rounding of probabilities cannot break prefix property.
+
+pub fn encode(stats: FileStats) -> HashMap<u8, Vec<u8>> {
+    let (quantities, total) = stats;
+    let probs = create_probability_map(&quantities, total);
+    let mut codes_map = HashMap::new();
+
+    let mut cumulative = 0;
+    for (byte, _) in probs {
+        let quantity = quantities[byte as usize];
+
+        // the only symbol has probability 1, still it needs at least one bit
+        let length = information_length(quantity, total).max(1);
+        codes_map.insert(
+            byte,
+            binary_expansion(cumulative as u128, total as u128, length),
+        );
+
+        cumulative += quantity;
+    }
+
+    codes_map
+}
@@ -9,6 +9,14 @@ pub fn encode(stats: FileStats) -> HashMap<u8, Vec<u8>> {
     let probs = probs.as_slice();
     let mut codes_map = HashMap::new();
 
+    // with less than two symbols there is nothing to split, still the symbol needs at least one bit
+    if probs.len() < 2 {
+        if let Some((byte, _)) = probs.first() {
+            codes_map.insert(*byte, vec![0]);
+        }
+        return codes_map;
+    }
+
     // actually encoding
     enc(&probs, 0, probs.len() - 1, &mut codes_map);
 
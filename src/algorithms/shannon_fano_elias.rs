@@ -0,0 +1,35 @@
+use std::collections::HashMap;
+
+use crate::types::FileStats;
+
+use super::{binary_expansion, create_probability_map, information_length};
+
+// Shannon-Fano-Elias code: symbol with probability p gets ceil(-log2 p) + 1 first binary digits
+// of the middle of its interval in the cumulative distribution. Symbols need no sorting, they are
+// taken in the order of `create_probability_map`; digits are computed from exact counts.
+
+pub fn encode(stats: FileStats) -> HashMap<u8, Vec<u8>> {
+    let (quantities, total) = stats;
+    let probs = create_probability_map(&quantities, total);
+    let mut codes_map = HashMap::new();
+
+    let mut cumulative = 0;
+    for (byte, _) in probs {
+        let quantity = quantities[byte as usize];
+
+        // middle of the interval is (cumulative + quantity / 2) / total
+        let length = information_length(quantity, total) + 1;
+        codes_map.insert(
+            byte,
+            binary_expansion(
+                2 * cumulative as u128 + quantity as u128,
+                2 * total as u128,
+                length,
+            ),
+        );
+
+        cumulative += quantity;
+    }
+
+    codes_map
+}
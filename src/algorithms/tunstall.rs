@@ -0,0 +1,242 @@
+use crate::bit_map::{BitMap, BitSource};
+use crate::types::FileStats;
+use min_max_heap::MinMaxHeap;
+use std::cmp::Ordering;
+use std::io::{Error, ErrorKind};
+
+// Tunstall coding (variable-to-fixed): input is split into phrases of a dictionary and every
+// phrase is written as its index of `code_bits` bits. Dictionary is a tree whose leaves are
+// phrases; it starts with all symbols as children of the root, and the most probable leaf is
+// expanded into children for every symbol while the leaves fit into 2^code_bits indices.
+// Since every node has all symbols as children, any input is parsed by walking the tree from the
+// root until a leaf. Leaves are indexed in lexicographic order of their phrases.
+// The input may end in the middle of a phrase; then any phrase that continues it is written,
+// decoder drops the excess bytes as it knows the original length.
+
+pub const MIN_CODE_BITS: u8 = 8;
+pub const MAX_CODE_BITS: u8 = 16;
+pub const DEFAULT_CODE_BITS: u8 = 12;
+
+const ROOT: usize = 0;
+
+pub fn check_code_bits(code_bits: u8) -> Result<(), Error> {
+    if !(MIN_CODE_BITS..=MAX_CODE_BITS).contains(&code_bits) {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!(
+                "Phrase index must take from {} to {} bits, got {}",
+                MIN_CODE_BITS, MAX_CODE_BITS, code_bits
+            ),
+        ));
+    }
+
+    Ok(())
+}
+
+/// frequencies of symbols scaled to fit 2 bytes, both coder and decoder build dictionary of them
+pub fn scale_frequencies(stats: &FileStats) -> [u32; 256] {
+    let max_quantity = stats.0.iter().max().copied().unwrap_or(0);
+    let mut frequencies = [0; 256];
+
+    for (frequency, quantity) in frequencies.iter_mut().zip(stats.0) {
+        if quantity != 0 {
+            // every present symbol must keep non-zero frequency
+            let scaled = quantity as u128 * u16::MAX as u128 / max_quantity as u128;
+            *frequency = (scaled as u32).max(1);
+        }
+    }
+
+    frequencies
+}
+
+/// leaf waiting for expansion, the most probable one goes first and the oldest one wins ties
+struct Candidate {
+    probability: f64,
+    node: usize,
+}
+
+impl Ord for Candidate {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.probability
+            .total_cmp(&other.probability)
+            .then(other.node.cmp(&self.node))
+    }
+}
+
+impl PartialOrd for Candidate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Candidate {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Candidate {}
+
+pub struct TunstallCode {
+    code_bits: u8,
+    rank: [usize; 256],        // position of byte among symbols of alphabet
+    children: Vec<Vec<usize>>, // children of every node by rank of their symbol, empty for leaves
+    parent: Vec<usize>,
+    symbol: Vec<u8>,    // the last byte of phrase of every node
+    code: Vec<usize>,   // index of every leaf
+    leaves: Vec<usize>, // leaf of every index
+}
+
+impl TunstallCode {
+    pub fn new(frequencies: &[u32; 256], code_bits: u8) -> Result<Self, Error> {
+        check_code_bits(code_bits)?;
+
+        let mut alphabet: Vec<u8> = (0..=255)
+            .filter(|byte| frequencies[*byte as usize] != 0)
+            .collect();
+        // tree does not grow with a single symbol, so symbols that never appear are added
+        for byte in 0..=255 {
+            if alphabet.len() >= 2 {
+                break;
+            }
+            if frequencies[byte as usize] == 0 {
+                alphabet.push(byte);
+            }
+        }
+        alphabet.sort();
+
+        let total: u64 = frequencies.iter().map(|f| *f as u64).sum();
+        let probability = |byte: u8| match total {
+            0 => 0.0,
+            _ => frequencies[byte as usize] as f64 / total as f64,
+        };
+
+        let mut rank = [usize::MAX; 256];
+        for (position, byte) in alphabet.iter().enumerate() {
+            rank[*byte as usize] = position;
+        }
+
+        let mut tree = Self {
+            code_bits,
+            rank,
+            children: vec![vec![]],
+            parent: vec![ROOT],
+            symbol: vec![0],
+            code: vec![0],
+            leaves: vec![],
+        };
+
+        let mut candidates = MinMaxHeap::new();
+        let mut leaf = Candidate {
+            probability: 1.0,
+            node: ROOT,
+        };
+        let mut leaf_count = 1;
+        // the root is expanded unconditionally, as the empty phrase is not a phrase
+        while leaf.node == ROOT || leaf_count + alphabet.len() - 1 <= 1 << code_bits {
+            for byte in &alphabet {
+                let child = tree.add_child(leaf.node, *byte);
+                candidates.push(Candidate {
+                    probability: leaf.probability * probability(*byte),
+                    node: child,
+                });
+            }
+            leaf_count += alphabet.len() - 1;
+            leaf = candidates.pop_max().unwrap();
+        }
+
+        // depth-first walk visits leaves in lexicographic order of phrases
+        let mut stack = vec![ROOT];
+        while let Some(node) = stack.pop() {
+            if tree.children[node].is_empty() {
+                tree.code[node] = tree.leaves.len();
+                tree.leaves.push(node);
+            }
+            stack.extend(tree.children[node].iter().rev());
+        }
+
+        Ok(tree)
+    }
+
+    fn add_child(&mut self, node: usize, byte: u8) -> usize {
+        let child = self.children.len();
+        self.children.push(vec![]);
+        self.parent.push(node);
+        self.symbol.push(byte);
+        self.code.push(0);
+        self.children[node].push(child);
+
+        child
+    }
+
+    pub fn code_bits(&self) -> u8 {
+        self.code_bits
+    }
+
+    pub fn phrase_count(&self) -> usize {
+        self.leaves.len()
+    }
+
+    /// reads one index and appends its phrase to `decoded`
+    pub fn decode(&self, bits: &mut impl BitSource, decoded: &mut Vec<u8>) -> Result<(), Error> {
+        let index = bits.read_bits(self.code_bits as usize)? as usize;
+        let mut node = match self.leaves.get(index) {
+            Some(node) => *node,
+            None => {
+                return Err(Error::new(
+                    ErrorKind::InvalidData,
+                    format!("Phrase {} is not in Tunstall dictionary", index),
+                ))
+            }
+        };
+
+        let start = decoded.len();
+        while node != ROOT {
+            decoded.push(self.symbol[node]);
+            node = self.parent[node];
+        }
+        decoded[start..].reverse();
+
+        Ok(())
+    }
+}
+
+pub struct TunstallEncoder {
+    code: TunstallCode,
+    node: usize, // node of the phrase read so far
+}
+
+impl TunstallEncoder {
+    pub fn new(code: TunstallCode) -> Self {
+        Self { code, node: ROOT }
+    }
+
+    /// every byte must have non-zero frequency in the dictionary
+    pub fn encode(&mut self, buf: &[u8], bits: &mut BitMap) {
+        for byte in buf {
+            self.node = self.code.children[self.node][self.code.rank[*byte as usize]];
+
+            if self.code.children[self.node].is_empty() {
+                self.emit(bits);
+            }
+        }
+    }
+
+    /// completes unfinished phrase with its first continuation
+    pub fn finish(&mut self, bits: &mut BitMap) {
+        if self.node != ROOT {
+            while !self.code.children[self.node].is_empty() {
+                self.node = self.code.children[self.node][0];
+            }
+            self.emit(bits);
+        }
+    }
+
+    fn emit(&mut self, bits: &mut BitMap) {
+        bits.add_value(
+            self.code.code[self.node] as u64,
+            self.code.code_bits as usize,
+        );
+        self.node = ROOT;
+    }
+}
@@ -0,0 +1,243 @@
+use crate::bit_map::{BitMap, BitSource};
+use std::io::{Error, ErrorKind};
+
+// Universal codes of integers: they need no probability model, every code is decodable by itself
+// and small numbers get short codes.
+// Elias gamma: N - 1 zeros followed by the N-bit binary value.
+// Elias delta: N in gamma code followed by the value without its leading one.
+// Elias omega: groups of binary values, each one is the length minus one of the next one,
+//   starting from 2 and ending with the value itself, followed by a zero.
+// Fibonacci: Zeckendorf representation (sum of non-adjacent Fibonacci numbers 1, 2, 3, 5, ...)
+//   from the smallest number, followed by an extra one, so every code ends with "11".
+// Golomb with parameter M: value / M in unary (ones ended by a zero) followed by value % M in
+//   truncated binary. Rice code of parameter K is Golomb code with M = 2^K.
+// Exp-Golomb of order K: value + 2^K in gamma code without the K lowest zeros of its prefix.
+// Gamma, delta, omega and Fibonacci codes take positive values, the rest take zero as well.
+
+/// Fibonacci numbers that fit u64: 1, 2, 3, 5, ...
+fn fibonacci_numbers() -> Vec<u64> {
+    let mut numbers: Vec<u64> = vec![1, 2];
+    while let Some(next) = numbers[numbers.len() - 1].checked_add(numbers[numbers.len() - 2]) {
+        numbers.push(next);
+    }
+
+    numbers
+}
+
+/// amount of significant bits of value
+fn bit_length(value: u128) -> usize {
+    (u128::BITS - value.leading_zeros()) as usize
+}
+
+/// adds `count` lowest bits of value, the most significant of them goes first
+fn add_wide_value(value: u128, count: usize, bits: &mut BitMap) {
+    for i in (0..count).rev() {
+        bits.add_bit(((value >> i) & 1) as u8);
+    }
+}
+
+fn read_zeros(bits: &mut impl BitSource, limit: usize) -> Result<usize, Error> {
+    let mut zeros = 0;
+    while bits.read_bit()? == 0 {
+        zeros += 1;
+        if zeros > limit {
+            return Err(too_long());
+        }
+    }
+
+    Ok(zeros)
+}
+
+fn too_long() -> Error {
+    Error::new(
+        ErrorKind::InvalidData,
+        "Universal code does not fit 64 bits",
+    )
+}
+
+fn check_positive(value: u64) {
+    assert_ne!(value, 0, "this universal code takes only positive values");
+}
+
+pub fn encode_gamma(value: u64, bits: &mut BitMap) {
+    check_positive(value);
+    let length = bit_length(value as u128);
+
+    bits.add_value(0, length - 1);
+    bits.add_value(value, length);
+}
+
+pub fn decode_gamma(bits: &mut impl BitSource) -> Result<u64, Error> {
+    let zeros = read_zeros(bits, 63)?;
+    Ok((1 << zeros) | bits.read_bits(zeros)?)
+}
+
+pub fn encode_delta(value: u64, bits: &mut BitMap) {
+    check_positive(value);
+    let length = bit_length(value as u128);
+
+    encode_gamma(length as u64, bits);
+    bits.add_value(value, length - 1);
+}
+
+pub fn decode_delta(bits: &mut impl BitSource) -> Result<u64, Error> {
+    let length = decode_gamma(bits)? as usize;
+    if length > 64 {
+        return Err(too_long());
+    }
+
+    Ok((1 << (length - 1)) | bits.read_bits(length - 1)?)
+}
+
+pub fn encode_omega(value: u64, bits: &mut BitMap) {
+    check_positive(value);
+
+    let mut groups = vec![];
+    let mut rest = value;
+    while rest > 1 {
+        groups.push(rest);
+        rest = bit_length(rest as u128) as u64 - 1;
+    }
+
+    for group in groups.iter().rev() {
+        bits.add_value(*group, bit_length(*group as u128));
+    }
+    bits.add_bit(0);
+}
+
+pub fn decode_omega(bits: &mut impl BitSource) -> Result<u64, Error> {
+    let mut value: u64 = 1;
+    // every group starts with one, the leading zero ends the code
+    while bits.read_bit()? == 1 {
+        if value > 63 {
+            return Err(too_long());
+        }
+        value = (1 << value) | bits.read_bits(value as usize)?;
+    }
+
+    Ok(value)
+}
+
+pub fn encode_fibonacci(value: u64, bits: &mut BitMap) {
+    check_positive(value);
+
+    let numbers = fibonacci_numbers();
+    let used = numbers
+        .iter()
+        .take_while(|number| **number <= value)
+        .count();
+
+    // greedy choice of the largest numbers gives Zeckendorf representation
+    let mut digits = vec![0; used];
+    let mut rest = value;
+    for index in (0..used).rev() {
+        if numbers[index] <= rest {
+            digits[index] = 1;
+            rest -= numbers[index];
+        }
+    }
+
+    bits.add_bit_sequence(&digits);
+    bits.add_bit(1);
+}
+
+pub fn decode_fibonacci(bits: &mut impl BitSource) -> Result<u64, Error> {
+    let numbers = fibonacci_numbers();
+    let mut value: u64 = 0;
+    let mut previous = 0;
+
+    for number in numbers {
+        let bit = bits.read_bit()?;
+        if bit == 1 && previous == 1 {
+            return Ok(value);
+        }
+        if bit == 1 {
+            value = value.checked_add(number).ok_or_else(too_long)?;
+        }
+        previous = bit;
+    }
+
+    // the terminating one may follow the largest number
+    match bits.read_bit()? {
+        1 if previous == 1 => Ok(value),
+        _ => Err(too_long()),
+    }
+}
+
+/// `modulus` must be positive; quotient is written in unary, so it should stay small
+pub fn encode_golomb(value: u64, modulus: u64, bits: &mut BitMap) {
+    check_positive(modulus);
+    let (quotient, remainder) = (value / modulus, value % modulus);
+
+    for _ in 0..quotient {
+        bits.add_bit(1);
+    }
+    bits.add_bit(0);
+
+    // truncated binary: the first `cutoff` remainders take one bit less
+    let length = bit_length(modulus as u128 - 1);
+    let cutoff = (1_u128 << length) - modulus as u128;
+    if (remainder as u128) < cutoff {
+        add_wide_value(remainder as u128, length - 1, bits);
+    } else {
+        add_wide_value(remainder as u128 + cutoff, length, bits);
+    }
+}
+
+pub fn decode_golomb(modulus: u64, bits: &mut impl BitSource) -> Result<u64, Error> {
+    check_positive(modulus);
+
+    let mut quotient: u64 = 0;
+    while bits.read_bit()? == 1 {
+        quotient += 1;
+    }
+
+    let length = bit_length(modulus as u128 - 1);
+    let cutoff = (1_u128 << length) - modulus as u128;
+    let remainder = match length {
+        0 => 0,
+        _ => {
+            let short = bits.read_bits(length - 1)? as u128;
+            if short < cutoff {
+                short
+            } else {
+                ((short << 1) | bits.read_bit()? as u128) - cutoff
+            }
+        }
+    };
+
+    quotient
+        .checked_mul(modulus)
+        .and_then(|value| value.checked_add(remainder as u64))
+        .ok_or_else(too_long)
+}
+
+/// Golomb code with modulus 2^`parameter`, so remainder is plain binary of `parameter` bits
+pub fn encode_rice(value: u64, parameter: u8, bits: &mut BitMap) {
+    encode_golomb(value, 1 << parameter, bits);
+}
+
+pub fn decode_rice(parameter: u8, bits: &mut impl BitSource) -> Result<u64, Error> {
+    decode_golomb(1 << parameter, bits)
+}
+
+pub fn encode_exp_golomb(value: u64, order: u8, bits: &mut BitMap) {
+    let shifted = value as u128 + (1 << order);
+    let length = bit_length(shifted);
+
+    bits.add_value(0, length - 1 - order as usize);
+    add_wide_value(shifted, length, bits);
+}
+
+pub fn decode_exp_golomb(order: u8, bits: &mut impl BitSource) -> Result<u64, Error> {
+    let zeros = read_zeros(bits, 64 - order as usize)?;
+    let length = zeros + order as usize;
+
+    let shifted = (1_u128 << length) | bits.read_bits(length)? as u128;
+    u64::try_from(shifted - (1 << order)).map_err(|_| too_long())
+}
+
+/// bits taken by Exp-Golomb code of the value
+pub fn exp_golomb_length(value: u64, order: u8) -> usize {
+    2 * bit_length(value as u128 + (1 << order)) - 1 - order as usize
+}
@@ -0,0 +1,145 @@
+use super::canonical::{self, CanonicalDecoder};
+use super::huffman;
+use crate::bit_map::BitSource;
+use std::collections::HashMap;
+use std::io::Error;
+
+// Word-based coding: text is cut into tokens, each one is either a run of word bytes (letters,
+// digits and bytes of multi-byte UTF-8 characters) or a run of the rest (spaces, punctuation).
+// Every distinct token gets Huffman code, and the vocabulary of tokens goes along with codes.
+// Concatenation of tokens is the original text, so decoder restores it byte to byte.
+// Tokens are cut at MAX_TOKEN_LENGTH bytes, so a token length always fits a byte.
+
+pub const MAX_TOKEN_LENGTH: usize = 255;
+
+/// distinct tokens of the text
+pub type Vocabulary = Vec<Vec<u8>>;
+
+pub fn is_word_byte(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || byte >= 0x80
+}
+
+/// cuts chunks of data into tokens, a token may start in one chunk and end in the next one
+pub struct Tokenizer {
+    pending: Vec<u8>, // bytes of the token that may continue
+}
+
+impl Tokenizer {
+    pub fn new() -> Self {
+        Self {
+            pending: Vec::with_capacity(MAX_TOKEN_LENGTH),
+        }
+    }
+
+    pub fn push<F>(&mut self, buf: &[u8], end_of_data: bool, mut on_token: F)
+    where
+        F: FnMut(&[u8]),
+    {
+        for byte in buf {
+            let continues = match self.pending.first() {
+                Some(first) => is_word_byte(*first) == is_word_byte(*byte),
+                None => true,
+            };
+            if !continues || self.pending.len() == MAX_TOKEN_LENGTH {
+                on_token(&self.pending);
+                self.pending.clear();
+            }
+            self.pending.push(*byte);
+        }
+
+        if end_of_data && !self.pending.is_empty() {
+            on_token(&self.pending);
+            self.pending.clear();
+        }
+    }
+}
+
+impl Default for Tokenizer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Huffman code of tokens. Tokens are numbered in lexicographic order and codes are canonical,
+/// so tokens in `tokens` follow by code length and then by their bytes
+pub struct WordCode {
+    pub tokens: Vocabulary,
+    codes: HashMap<Vec<u8>, Vec<u8>>,
+    lengths: HashMap<u32, usize>,
+}
+
+impl WordCode {
+    pub fn build(quantities: &HashMap<Vec<u8>, u64>) -> Self {
+        let mut tokens: Vec<&Vec<u8>> = quantities.keys().collect();
+        tokens.sort();
+
+        let token_quantities: Vec<u64> = tokens.iter().map(|token| quantities[*token]).collect();
+        let id_codes: HashMap<u32, Vec<u8>> = huffman::encode_indexed(&token_quantities)
+            .into_iter()
+            .map(|(id, code)| (id as u32, code))
+            .collect();
+        let id_codes = canonical::canonize(&id_codes);
+        let lengths: HashMap<u32, usize> = id_codes
+            .iter()
+            .map(|(id, code)| (*id, code.len()))
+            .collect();
+
+        let codes = id_codes
+            .into_iter()
+            .map(|(id, code)| (tokens[id as usize].clone(), code))
+            .collect();
+
+        Self {
+            tokens: canonical::canonical_order(&lengths)
+                .iter()
+                .map(|(id, _)| tokens[*id as usize].clone())
+                .collect(),
+            codes,
+            lengths,
+        }
+    }
+
+    pub fn code(&self, token: &[u8]) -> &Vec<u8> {
+        &self.codes[token]
+    }
+
+    /// amount of codes of every length from 1 to the longest one
+    pub fn length_counts(&self) -> Vec<usize> {
+        canonical::length_counts(&self.lengths)
+    }
+}
+
+pub struct WordDecoder {
+    tokens: Vocabulary,
+    decoder: CanonicalDecoder<u32>,
+}
+
+impl WordDecoder {
+    /// `tokens` go in canonical order, `counts` are amounts of codes of every length
+    pub fn new(tokens: Vocabulary, counts: &[usize]) -> Self {
+        let mut lengths = HashMap::new();
+        let mut id = 0;
+        for (length, count) in counts.iter().enumerate() {
+            for _ in 0..*count {
+                lengths.insert(id, length + 1);
+                id += 1;
+            }
+        }
+
+        Self {
+            tokens,
+            decoder: CanonicalDecoder::new(&lengths),
+        }
+    }
+
+    /// reads one code and appends its token to `decoded`
+    pub fn decode(
+        &mut self,
+        bits: &mut impl BitSource,
+        decoded: &mut Vec<u8>,
+    ) -> Result<(), Error> {
+        let id = self.decoder.decode(bits)?;
+        decoded.extend_from_slice(&self.tokens[id as usize]);
+        Ok(())
+    }
+}
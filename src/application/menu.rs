@@ -1,5 +1,11 @@
 use crate::{
-    types::{CodeType, EncodingSettings},
+    algorithms::{
+        convolutional, hamming, huffman, interleaver, lzss, lzw, ngram, ppm, reed_solomon, tunstall,
+    },
+    types::{
+        CodeType, Coding, EncodingSettings, ErrorCorrection, ErrorCorrectionScheme, Interleaving,
+        InterleavingScheme,
+    },
     utils::{clear, get_file, logic, pause, terminal::get_line_from_user},
 };
 use std::{
@@ -64,11 +70,27 @@ fn print_options() {
 }
 
 fn print_code_types() {
-    println!("1. Shannon Fano");
-    println!("2. Huffman");
+    println!("1. Huffman");
+    println!("2. Shannon Fano");
+    println!("3. Adaptive Huffman (single pass)");
+    println!("4. Arithmetic");
+    println!("5. Adaptive arithmetic (single pass)");
+    println!("6. rANS (asymmetric numeral systems)");
+    println!("7. LZSS + Huffman (for repetitive data)");
+    println!("8. LZW");
+    println!("9. BWT + move-to-front + Huffman (bzip2 style)");
+    println!("10. Order-1 context Huffman (table per previous byte)");
+    println!("11. PPM (best ratio on text, slow)");
+    println!("12. Shannon");
+    println!("13. Shannon-Fano-Elias");
+    println!("14. D-ary Huffman (digits packed into bits)");
+    println!("15. Hu-Tucker (codes keep order of bytes)");
+    println!("16. Tunstall (fixed width codes of variable phrases)");
+    println!("17. Huffman over n-grams (pairs or triples of bytes)");
+    println!("18. Huffman over words (for natural-language text)");
 }
 
-fn parse_option_from_str<T: Copy>(map: &HashMap<u8, T>, opt: &str) -> Result<T, String> {
+fn parse_option_from_str<T: Clone>(map: &HashMap<u8, T>, opt: &str) -> Result<T, String> {
     let option = match opt.parse::<u8>() {
         Ok(val) => val,
         Err(_) => return Err(String::from("Option must be a digit")),
@@ -111,12 +133,43 @@ fn encoding_prerequisites() -> Result<EncodingSettings, Error> {
         }
     };
 
-    let hamming_code_length = choose_hamming_code_length()?;
+    let coding = match code_type {
+        CodeType::Huffman => Coding::Huffman {
+            max_code_length: choose_max_code_length()?,
+        },
+        CodeType::Lzss => Coding::Lzss {
+            window_bits: choose_lzss_window_bits()?,
+        },
+        CodeType::Lzw => Coding::Lzw {
+            max_code_bits: choose_lzw_max_code_bits()?,
+        },
+        CodeType::Ppm => Coding::Ppm {
+            order: choose_ppm_order()?,
+        },
+        CodeType::DaryHuffman => Coding::DaryHuffman {
+            arity: choose_huffman_arity()?,
+        },
+        CodeType::Tunstall => Coding::Tunstall {
+            code_bits: choose_tunstall_code_bits()?,
+        },
+        CodeType::NgramHuffman => Coding::NgramHuffman {
+            length: choose_ngram_length()?,
+        },
+        other => Coding::from(other),
+    };
+
+    let error_correction = choose_error_correction()?;
+
+    let interleaving = match error_correction {
+        ErrorCorrection::Hamming { .. } => choose_interleaving()?,
+        _ => Interleaving::None,
+    };
 
     Ok(EncodingSettings {
-        code_type,
         file_info,
-        hamming_code_length,
+        coding,
+        error_correction,
+        interleaving,
     })
 }
 
@@ -127,7 +180,26 @@ fn choose_encoding_type() -> Option<CodeType> {
         String::from_utf8(get_line_from_user().into_bytes()).unwrap_or("error".to_string());
 
     match parse_option_from_str(
-        &HashMap::from([(1_u8, CodeType::Huffman), (2_u8, CodeType::ShannonFano)]),
+        &HashMap::from([
+            (1_u8, CodeType::Huffman),
+            (2_u8, CodeType::ShannonFano),
+            (3_u8, CodeType::AdaptiveHuffman),
+            (4_u8, CodeType::Arithmetic),
+            (5_u8, CodeType::AdaptiveArithmetic),
+            (6_u8, CodeType::Ans),
+            (7_u8, CodeType::Lzss),
+            (8_u8, CodeType::Lzw),
+            (9_u8, CodeType::Bwt),
+            (10_u8, CodeType::ContextHuffman),
+            (11_u8, CodeType::Ppm),
+            (12_u8, CodeType::Shannon),
+            (13_u8, CodeType::ShannonFanoElias),
+            (14_u8, CodeType::DaryHuffman),
+            (15_u8, CodeType::HuTucker),
+            (16_u8, CodeType::Tunstall),
+            (17_u8, CodeType::NgramHuffman),
+            (18_u8, CodeType::WordHuffman),
+        ]),
         option.trim(),
     ) {
         Ok(code_type) => Some(code_type),
@@ -138,33 +210,334 @@ fn choose_encoding_type() -> Option<CodeType> {
     }
 }
 
-fn choose_hamming_code_length() -> Result<Option<u8>, Error> {
-    if !ask_use_hamming_code()? {
+/// asks whether to change a coder parameter and reads it until `check` accepts it
+fn choose_parameter(
+    question: &str,
+    prompt: String,
+    check: impl Fn(u8) -> Result<(), Error>,
+) -> Result<Option<u8>, Error> {
+    if !ask_yes_no(question)? {
         return Ok(None);
     };
 
     loop {
         clear();
-        println!("Input size for hamming code (7 - 255)");
+        println!("{}", prompt);
         let input = get_line_from_user();
         let input = input.trim();
 
         match input.parse::<u8>() {
-            Ok(ham_code_lenght) => {
-                if ham_code_lenght >= 7 {
-                    return Ok(Some(ham_code_lenght));
-                } else {
-                    pause("Code length must be in range of 7 - 255. Press any key...")
+            Ok(value) => match check(value) {
+                Ok(_) => return Ok(Some(value)),
+                Err(err) => pause(format!("{}. Press any key...", err).as_str()),
+            },
+            Err(err) => pause(format!("{}. Press any key...", err).as_str()),
+        }
+    }
+}
+
+fn choose_max_code_length() -> Result<Option<u8>, Error> {
+    choose_parameter(
+        "Limit length of Huffman codes? (y/n)",
+        "Input maximal code length in bits (1 - 255, 15 suits table decoders)".to_string(),
+        |max_length| {
+            logic::check_coding(&Coding::Huffman {
+                max_code_length: Some(max_length),
+            })
+        },
+    )
+}
+
+fn choose_lzss_window_bits() -> Result<Option<u8>, Error> {
+    choose_parameter(
+        "Change size of LZSS window? (y/n)",
+        format!(
+            "Input window size as power of two ({} - {}, default is {})",
+            lzss::MIN_WINDOW_BITS,
+            lzss::MAX_WINDOW_BITS,
+            lzss::DEFAULT_WINDOW_BITS
+        ),
+        |window_bits| lzss::window_size(window_bits).map(|_| ()),
+    )
+}
+
+fn choose_lzw_max_code_bits() -> Result<Option<u8>, Error> {
+    choose_parameter(
+        "Change maximal width of LZW codes? (y/n)",
+        format!(
+            "Input maximal code width in bits ({} - {}, default is {})",
+            lzw::MIN_CODE_BITS,
+            lzw::MAX_CODE_BITS,
+            lzw::DEFAULT_CODE_BITS
+        ),
+        lzw::check_max_bits,
+    )
+}
+
+fn choose_ppm_order() -> Result<Option<u8>, Error> {
+    choose_parameter(
+        "Change order of PPM model? (y/n)",
+        format!(
+            "Input the longest context in bytes ({} - {}, default is {})",
+            ppm::MIN_ORDER,
+            ppm::MAX_ORDER,
+            ppm::DEFAULT_ORDER
+        ),
+        ppm::check_order,
+    )
+}
+
+fn choose_huffman_arity() -> Result<Option<u8>, Error> {
+    choose_parameter(
+        "Change amount of code digits? (y/n)",
+        format!(
+            "Input amount of digits of code alphabet ({} - {}, default is {})",
+            huffman::MIN_ARITY,
+            huffman::MAX_ARITY,
+            huffman::DEFAULT_ARITY
+        ),
+        huffman::check_arity,
+    )
+}
+
+fn choose_tunstall_code_bits() -> Result<Option<u8>, Error> {
+    choose_parameter(
+        "Change size of Tunstall dictionary? (y/n)",
+        format!(
+            "Input width of phrase index in bits ({} - {}, default is {})",
+            tunstall::MIN_CODE_BITS,
+            tunstall::MAX_CODE_BITS,
+            tunstall::DEFAULT_CODE_BITS
+        ),
+        tunstall::check_code_bits,
+    )
+}
+
+fn choose_ngram_length() -> Result<Option<u8>, Error> {
+    choose_parameter(
+        "Change length of n-grams? (y/n)",
+        format!(
+            "Input length of n-gram in bytes ({} - {}, default is {})",
+            ngram::MIN_LENGTH,
+            ngram::MAX_LENGTH,
+            ngram::DEFAULT_LENGTH
+        ),
+        ngram::check_length,
+    )
+}
+
+fn choose_error_correction() -> Result<ErrorCorrection, Error> {
+    if !ask_yes_no("Protect data with error correcting codes? (y/n)")? {
+        return Ok(ErrorCorrection::None);
+    };
+
+    loop {
+        clear();
+        println!("Choose error correcting code");
+        println!("1. Hamming (corrects a bit per block)");
+        println!("2. Extended Hamming (corrects a bit and detects two bits per block)");
+        println!("3. Reed-Solomon (corrects damaged bytes, suits burst errors)");
+        println!("4. Convolutional (Viterbi decoding, suits scattered errors)");
+
+        let option = get_line_from_user();
+        match parse_option_from_str(
+            &HashMap::from([
+                (1_u8, ErrorCorrectionScheme::Hamming),
+                (2_u8, ErrorCorrectionScheme::ExtendedHamming),
+                (3_u8, ErrorCorrectionScheme::ReedSolomon),
+                (4_u8, ErrorCorrectionScheme::Convolutional),
+            ]),
+            option.trim(),
+        ) {
+            Ok(ErrorCorrectionScheme::Hamming) => return Ok(choose_hamming_code_length(false)),
+            Ok(ErrorCorrectionScheme::ExtendedHamming) => {
+                return Ok(choose_hamming_code_length(true))
+            }
+            Ok(ErrorCorrectionScheme::ReedSolomon) => return Ok(choose_reed_solomon_lengths()),
+            Ok(_) => return Ok(choose_convolutional_code()),
+            Err(message) => pause(format!("{}. Press any key...", message).as_str()),
+        }
+    }
+}
+
+fn choose_hamming_code_length(extended: bool) -> ErrorCorrection {
+    let min_length = hamming::MIN_CODE_LENGTH + extended as u8;
+
+    loop {
+        clear();
+        println!(
+            "Input size for hamming code ({} - 255, not a power of two{})",
+            min_length,
+            if extended { " plus one" } else { "" }
+        );
+        let input = get_line_from_user();
+        let input = input.trim();
+
+        match input.parse::<u8>() {
+            Ok(ham_code_lenght) => match hamming::check_code_length(ham_code_lenght, extended) {
+                Ok(()) => {
+                    return ErrorCorrection::Hamming {
+                        code_length: ham_code_lenght,
+                        extended,
+                    }
                 }
+                Err(err) => pause(format!("{}. Press any key...", err).as_str()),
+            },
+            Err(err) => pause(format!("{}. Press any key...", err).as_str()),
+        }
+    }
+}
+
+fn choose_reed_solomon_lengths() -> ErrorCorrection {
+    loop {
+        clear();
+        println!(
+            "Input block length n and data length k in bytes, separated by space (k < n <= 255, default is {} {})",
+            reed_solomon::DEFAULT_BLOCK_LENGTH,
+            reed_solomon::DEFAULT_DATA_LENGTH
+        );
+        let input = get_line_from_user();
+        let lengths: Vec<&str> = input.split_whitespace().collect();
+        if lengths.len() != 2 {
+            pause("Two numbers are expected. Press any key...");
+            continue;
+        }
+
+        match (lengths[0].parse::<u8>(), lengths[1].parse::<u8>()) {
+            (Ok(n), Ok(k)) => match reed_solomon::check_parameters(n, k) {
+                Ok(()) => return ErrorCorrection::ReedSolomon { n, k },
+                Err(err) => pause(format!("{}. Press any key...", err).as_str()),
+            },
+            (Err(err), _) | (_, Err(err)) => pause(format!("{}. Press any key...", err).as_str()),
+        }
+    }
+}
+
+fn choose_convolutional_code() -> ErrorCorrection {
+    loop {
+        clear();
+        println!(
+            "Input constraint length ({} - {}) and 2 - {} generator polynomials in octal, separated by space (e.g. 7 171 133)",
+            convolutional::MIN_CONSTRAINT_LENGTH,
+            convolutional::MAX_CONSTRAINT_LENGTH,
+            convolutional::MAX_OUTPUTS
+        );
+        let input = get_line_from_user();
+        let mut numbers = input.split_whitespace();
+
+        let constraint_length = match numbers.next().map(|number| number.parse::<u8>()) {
+            Some(Ok(constraint_length)) => constraint_length,
+            Some(Err(err)) => {
+                pause(format!("{}. Press any key...", err).as_str());
+                continue;
             }
+            None => {
+                pause("Constraint length is expected. Press any key...");
+                continue;
+            }
+        };
+        let polynomials: Result<Vec<u16>, _> = numbers
+            .map(|number| u16::from_str_radix(number, 8))
+            .collect();
+        let polynomials = match polynomials {
+            Ok(polynomials) => polynomials,
+            Err(err) => {
+                pause(format!("{}. Press any key...", err).as_str());
+                continue;
+            }
+        };
+        if let Err(err) = convolutional::check_parameters(constraint_length, &polynomials, None) {
+            pause(format!("{}. Press any key...", err).as_str());
+            continue;
+        }
+
+        let puncturing = match polynomials.len() {
+            2 => choose_puncturing(),
+            _ => None,
+        };
+        return ErrorCorrection::Convolutional {
+            constraint_length,
+            polynomials,
+            puncturing,
+        };
+    }
+}
+
+/// puncturing patterns of rate 1/2 code
+fn choose_puncturing() -> Option<Vec<Vec<u8>>> {
+    loop {
+        clear();
+        println!("Choose code rate");
+        println!("1. 1/2 (no puncturing)");
+        println!("2. 2/3");
+        println!("3. 3/4");
+
+        let option = get_line_from_user();
+        match parse_option_from_str(
+            &HashMap::from([
+                (1_u8, None),
+                (2_u8, Some(vec![vec![1, 1], vec![1, 0]])),
+                (3_u8, Some(vec![vec![1, 1, 0], vec![1, 0, 1]])),
+            ]),
+            option.trim(),
+        ) {
+            Ok(puncturing) => return puncturing,
+            Err(message) => pause(format!("{}. Press any key...", message).as_str()),
+        }
+    }
+}
+
+fn choose_interleaving() -> Result<Interleaving, Error> {
+    if !ask_yes_no("Interleave codewords to withstand burst errors? (y/n)")? {
+        return Ok(Interleaving::None);
+    };
+
+    let scheme = loop {
+        clear();
+        println!("Choose interleaver");
+        println!("1. Block (codewords are written by columns)");
+        println!("2. Convolutional (codeword bits are delayed by branches)");
+
+        let option = get_line_from_user();
+        match parse_option_from_str(
+            &HashMap::from([
+                (1_u8, InterleavingScheme::Block),
+                (2_u8, InterleavingScheme::Convolutional),
+            ]),
+            option.trim(),
+        ) {
+            Ok(scheme) => break scheme,
+            Err(message) => pause(format!("{}. Press any key...", message).as_str()),
+        }
+    };
+
+    loop {
+        clear();
+        println!(
+            "Input interleaver depth ({} - 255), bursts up to this many bits are spread over different codewords",
+            interleaver::MIN_DEPTH
+        );
+        let input = get_line_from_user();
+        let input = input.trim();
+
+        match input.parse::<u8>() {
+            Ok(depth) => match interleaver::check_depth(depth) {
+                Ok(()) => {
+                    return Ok(match scheme {
+                        InterleavingScheme::Convolutional => Interleaving::Convolutional { depth },
+                        _ => Interleaving::Block { depth },
+                    })
+                }
+                Err(err) => pause(format!("{}. Press any key...", err).as_str()),
+            },
             Err(err) => pause(format!("{}. Press any key...", err).as_str()),
         }
     }
 }
 
-fn ask_use_hamming_code() -> Result<bool, Error> {
+fn ask_yes_no(question: &str) -> Result<bool, Error> {
     loop {
-        println!("Use hamming codes to compress data? (y/n)");
+        println!("{}", question);
 
         let ans = get_line_from_user().to_lowercase();
         let ans = ans.trim();
@@ -1,4 +1,31 @@
-use std::{error::Error, fmt::Display, fs::File, io::Write};
+use std::{error::Error, fmt::Display, fs::File, io, io::Write};
+
+/// source of bits for decoders that pull bits one by one
+pub trait BitSource {
+    fn read_bit(&mut self) -> Result<u8, io::Error>;
+
+    /// reads `count` bits, first one becomes the most significant
+    fn read_bits(&mut self, count: usize) -> Result<u64, io::Error> {
+        let mut value = 0;
+        for _ in 0..count {
+            value = (value << 1) | self.read_bit()? as u64;
+        }
+
+        Ok(value)
+    }
+}
+
+/// lets decoders read from in-memory array of bits (1 0)
+impl BitSource for std::slice::Iter<'_, u8> {
+    fn read_bit(&mut self) -> Result<u8, io::Error> {
+        self.next().copied().ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "Bit sequence ended unexpectedly",
+            )
+        })
+    }
+}
 
 pub struct BitMap {
     byte_buffer: Vec<u8>,
@@ -98,6 +125,28 @@ impl BitMap {
         bits
     }
 
+    /// returns all added bits including those of incomplete byte and clears its buffers
+    pub fn take_all_bits(&mut self) -> Vec<u8> {
+        let mut bits = self.get_all_bits();
+        for i in 0..self.current_bit {
+            bits.push(if self.current_byte & (1 << i) == 0 {
+                0
+            } else {
+                1
+            });
+        }
+
+        self.clear();
+        bits
+    }
+
+    /// adds `count` lowest bits of value, the most significant of them goes first
+    pub fn add_value(&mut self, value: u64, count: usize) {
+        for i in (0..count).rev() {
+            self.add_bit(((value >> i) & 1) as u8);
+        }
+    }
+
     pub fn get_bits(&self, count: usize) -> Vec<u8> {
         let mut bits = vec![];
         let mut count = count;
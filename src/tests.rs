@@ -2,12 +2,182 @@
 #![allow(unused)]
 
 use crate::{
-    algorithms::{hamming, huffman},
+    algorithms::{
+        adaptive_huffman::AdaptiveHuffman,
+        ans::{self, AnsDecoder, AnsEncoder, AnsTable},
+        arithmetic::{ArithmeticDecoder, ArithmeticEncoder, FrequencyModel},
+        bch::{self, Bch},
+        bwt::{self, Symbol},
+        canonical::{self, CanonicalDecoder},
+        context::{self, ContextCodes, ContextDecoder, ContextStats},
+        convolutional::{self, ConvolutionalCode},
+        crc32, hamming, hu_tucker, huffman,
+        interleaver::{self, ConvolutionalInterleaver},
+        lzss::{self, LzssDecoder, LzssEncoder, Token},
+        lzw::{self, LzwDecoder, LzwEncoder},
+        ngram::{self, Ngrams},
+        ppm::{self, PpmModel},
+        reed_solomon::{self, ReedSolomon},
+        shannon, shannon_fano, shannon_fano_elias,
+        tunstall::{self, TunstallCode, TunstallEncoder},
+        universal,
+        words::{Tokenizer, WordCode, WordDecoder},
+    },
     bit_map::BitMap,
-    types::{CodeType, EncodingSettings},
-    utils::{clear, logic::encode_file, pause, print_entries_of_current_dir, terminal},
+    types::{CodeType, Coding, EncodingSettings, ErrorCorrection, FileStats, Interleaving},
+    utils::{
+        channel, clear,
+        constants::{ARCHIVE_EXTENSION, FORMAT_VERSION},
+        header::ArchiveHeader,
+        logic::{check_coding, decode_file, encode_file, ChecksumMismatchError},
+        pause, print_entries_of_current_dir, terminal,
+    },
 };
-use std::{cmp::min, fs::File};
+use std::{
+    cmp::min,
+    collections::HashMap,
+    fs::{self, File},
+    io::{ErrorKind, Write},
+    os::unix::fs::FileExt,
+    path::PathBuf,
+};
+
+/// creates empty directory for test artifacts; its name has no '.' or '_' so decoded file names stay predictable
+fn scratch_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("tik{}", name));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// quantities of 'a' to 'f' that sum to 100, a textbook example of Huffman coding
+fn sample_stats() -> FileStats {
+    let mut quantities = [0_u64; 256];
+    for (byte, quantity) in [
+        (b'a', 45),
+        (b'b', 13),
+        (b'c', 12),
+        (b'd', 16),
+        (b'e', 9),
+        (b'f', 5),
+    ] {
+        quantities[byte as usize] = quantity;
+    }
+    (quantities, 100)
+}
+
+/// byte 7 repeated 10 times
+fn single_symbol_stats() -> FileStats {
+    let mut quantities = [0_u64; 256];
+    quantities[7] = 10;
+    (quantities, 10)
+}
+
+/// average length of codes in digits, symbols are weighted by their quantities
+fn average_length(codes: &HashMap<u8, Vec<u8>>, stats: &FileStats) -> f64 {
+    codes
+        .iter()
+        .map(|(symbol, code)| (stats.0[*symbol as usize] * code.len() as u64) as f64)
+        .sum::<f64>()
+        / stats.1 as f64
+}
+
+/// entropy of stats in bits per symbol
+fn entropy(stats: &FileStats) -> f64 {
+    stats
+        .0
+        .iter()
+        .filter(|q| **q != 0)
+        .map(|q| *q as f64 / stats.1 as f64)
+        .map(|p| -p * p.log2())
+        .sum()
+}
+
+/// 5000 bytes of 17 values with uneven frequencies
+fn sample_data() -> Vec<u8> {
+    (0..5000_u32).map(|i| (i * i % 251 % 17) as u8).collect()
+}
+
+fn hamming_code(code_length: u8) -> ErrorCorrection {
+    ErrorCorrection::Hamming {
+        code_length,
+        extended: false,
+    }
+}
+
+/// default settings for encoding file at `path`, tests override the fields they check
+fn settings(path: &str, code_type: CodeType) -> EncodingSettings {
+    EncodingSettings {
+        file_info: (File::open(path).unwrap(), path.to_owned()),
+        coding: Coding::from(code_type),
+        error_correction: ErrorCorrection::None,
+        interleaving: Interleaving::None,
+    }
+}
+
+/// encodes data into archive, zeroes frequencies of the table that follows archive header and
+/// returns error of decoding it
+fn decode_with_zero_frequencies(name: &str, data: &[u8], code_type: CodeType) -> std::io::Error {
+    let dir = scratch_dir(name);
+    let path = dir.join("sample.txt").to_str().unwrap().to_owned();
+    fs::write(&path, data).unwrap();
+    encode_file(settings(&path, code_type)).unwrap();
+
+    let archive_path = path.clone() + ARCHIVE_EXTENSION;
+    let archive = File::options()
+        .read(true)
+        .write(true)
+        .open(&archive_path)
+        .unwrap();
+    let (_, offset) = ArchiveHeader::read_from(&archive).unwrap();
+    let mut count = [0_u8; 2];
+    archive.read_exact_at(&mut count, offset as u64).unwrap();
+    // entries are symbol (1 byte) and frequency (2 bytes)
+    for entry in 0..u16::from_le_bytes(count) as u64 {
+        archive
+            .write_at(&[0, 0], offset as u64 + 2 + 3 * entry + 1)
+            .unwrap();
+    }
+
+    let err = decode_file(Some((File::open(&archive_path).unwrap(), archive_path))).unwrap_err();
+    fs::remove_dir_all(&dir).unwrap();
+    err
+}
+
+/// encodes data into archive and decodes it back, returns decoded bytes
+fn roundtrip(
+    name: &str,
+    data: &[u8],
+    code_type: CodeType,
+    hamming_code_length: Option<u8>,
+) -> Vec<u8> {
+    roundtrip_with(name, data, |settings| {
+        settings.coding = Coding::from(code_type);
+        settings.error_correction = hamming_code_length.map_or(ErrorCorrection::None, hamming_code);
+    })
+}
+
+/// same as `roundtrip`, but settings are adjusted by `configure`
+fn roundtrip_with(
+    name: &str,
+    data: &[u8],
+    configure: impl FnOnce(&mut EncodingSettings),
+) -> Vec<u8> {
+    let dir = scratch_dir(name);
+    let path = dir.join("sample.txt").to_str().unwrap().to_owned();
+    fs::write(&path, data).unwrap();
+
+    let mut settings = settings(&path, CodeType::Huffman);
+    configure(&mut settings);
+    encode_file(settings).unwrap();
+
+    let archive_path = path.clone() + ARCHIVE_EXTENSION;
+    decode_file(Some((File::open(&archive_path).unwrap(), archive_path))).unwrap();
+
+    let decoded = fs::read(dir.join("sample_1.txt")).unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+    decoded
+}
 
 #[test]
 fn test_bit_container() {
@@ -93,15 +263,8 @@ fn test_flush() {
 #[test]
 fn test_shannon_fano_encoding() {
     let path = String::from("/home/nazar/prg/rust/labs/tik/samples/en/test_1.txt");
-    let file = File::open(&path).unwrap();
-
-    let settings = EncodingSettings {
-        file_info: (file, path),
-        code_type: CodeType::ShannonFano,
-        hamming_code_length: None,
-    };
 
-    encode_file(settings);
+    encode_file(settings(&path, CodeType::ShannonFano));
 }
 
 #[test]
@@ -182,3 +345,1736 @@ fn playground() {
         )
     }
 }
+
+#[test]
+fn test_archive_header_roundtrip() {
+    let dir = scratch_dir("header");
+    let path = dir.join("header.nk");
+
+    let header = ArchiveHeader::new(CodeType::ShannonFano, hamming_code(15), 1234);
+    header.write_to(&mut File::create(&path).unwrap()).unwrap();
+
+    let (read_header, offset) = ArchiveHeader::read_from(&File::open(&path).unwrap()).unwrap();
+    assert_eq!(header, read_header);
+    assert_eq!(offset, fs::metadata(&path).unwrap().len() as usize);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_archive_header_rejects_unknown_version() {
+    let dir = scratch_dir("version");
+    let path = dir.join("version.nk");
+
+    let header = ArchiveHeader::new(CodeType::Huffman, ErrorCorrection::None, 0);
+    header.write_to(&mut File::create(&path).unwrap()).unwrap();
+
+    // version byte follows 4 magic bytes
+    let file = File::options().write(true).open(&path).unwrap();
+    file.write_at(&[FORMAT_VERSION + 1], 4).unwrap();
+
+    let err = ArchiveHeader::read_from(&File::open(&path).unwrap()).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+    assert!(err
+        .to_string()
+        .contains("Unsupported archive format version"));
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_input_starting_with_former_hamming_key() {
+    let data = b"hmmcl looks like the old hamming marker";
+    assert_eq!(
+        roundtrip("hmmcl", data, CodeType::Huffman, None)[..data.len()],
+        data[..]
+    );
+}
+
+#[test]
+fn test_roundtrip_stops_at_original_length() {
+    // 'a' gets an all-zero code, so padding bits would decode into extra 'a's
+    let mut data = vec![b'a'; 50];
+    data.extend_from_slice(b"bbbbbbbbbbccccccd");
+
+    assert_eq!(
+        roundtrip("exactsf", &data, CodeType::ShannonFano, None),
+        data
+    );
+    assert_eq!(roundtrip("exacthf", &data, CodeType::Huffman, None), data);
+    assert_eq!(
+        roundtrip("exacthm", &data, CodeType::Huffman, Some(7)),
+        data
+    );
+}
+
+#[test]
+fn test_roundtrip_degenerate_inputs() {
+    assert_eq!(roundtrip("empty", b"", CodeType::Huffman, None), b"");
+    assert_eq!(
+        roundtrip("single", b"zzzzzzz", CodeType::Huffman, None),
+        b"zzzzzzz"
+    );
+    assert_eq!(
+        roundtrip("singlesf", b"q", CodeType::ShannonFano, Some(12)),
+        b"q"
+    );
+}
+
+#[test]
+fn test_canonical_codes() {
+    for codes in [
+        huffman::encode(sample_stats()),
+        shannon_fano::encode(sample_stats()),
+    ] {
+        let canonical_codes = canonical::canonize(&codes);
+
+        // lengths are kept, only bit patterns change
+        for (symbol, code) in &codes {
+            assert_eq!(code.len(), canonical_codes[symbol].len());
+        }
+
+        let lengths: HashMap<u8, usize> =
+            canonical_codes.iter().map(|(s, c)| (*s, c.len())).collect();
+        let mut decoder = CanonicalDecoder::new(&lengths);
+        for (symbol, code) in &canonical_codes {
+            let (last, init) = code.split_last().unwrap();
+            for bit in init {
+                assert_eq!(decoder.push_bit(*bit).unwrap(), None);
+            }
+            assert_eq!(decoder.push_bit(*last).unwrap(), Some(*symbol));
+        }
+    }
+
+    let lengths = HashMap::from([(b'x', 2), (b'a', 2), (b'q', 1)]);
+    let codes = canonical::assign_codes(&lengths);
+    assert_eq!(codes[&b'q'], vec![0]);
+    assert_eq!(codes[&b'a'], vec![1, 0]);
+    assert_eq!(codes[&b'x'], vec![1, 1]);
+
+    // corrupted counts of code lengths are rejected instead of overflowing canonical decoder
+    let mut overflowing = vec![0; 200];
+    overflowing[0] = 1;
+    overflowing[199] = 1;
+    for (code_type, prefix, counts) in [
+        (CodeType::Huffman, vec![], overflowing),
+        (
+            CodeType::DaryHuffman,
+            vec![3],
+            [vec![0; 80], vec![1]].concat(),
+        ),
+        // three codes of length 1 do not fit binary code
+        (CodeType::Huffman, vec![], vec![3]),
+    ] {
+        let dir = scratch_dir("lengthcounts");
+        let archive_path = dir.join("sample.txt").to_str().unwrap().to_owned() + ARCHIVE_EXTENSION;
+        let mut archive = File::create(&archive_path).unwrap();
+        ArchiveHeader::new(code_type, ErrorCorrection::None, 1)
+            .write_to(&mut archive)
+            .unwrap();
+
+        let mut header = BitMap::new();
+        universal::encode_exp_golomb(counts.len() as u64, 0, &mut header);
+        for count in &counts {
+            universal::encode_exp_golomb(*count, 0, &mut header);
+        }
+        archive.write_all(&prefix).unwrap();
+        header.flush_to_file(&mut archive).unwrap();
+        archive.write_all(&[b'a', b'b', b'c', 0, 0]).unwrap();
+
+        let err =
+            decode_file(Some((File::open(&archive_path).unwrap(), archive_path))).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        assert_eq!(err.to_string(), "Dictionary header is corrupted");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
+
+#[test]
+fn test_archive_is_deterministic() {
+    let dir = scratch_dir("deterministic");
+    let path = dir.join("sample.txt").to_str().unwrap().to_owned();
+    fs::write(
+        &path,
+        b"the same input must give the same archive every time",
+    )
+    .unwrap();
+
+    let mut archives = vec![];
+    for _ in 0..2 {
+        encode_file(settings(&path, CodeType::Huffman)).unwrap();
+        archives.push(fs::read(path.clone() + ARCHIVE_EXTENSION).unwrap());
+    }
+    assert_eq!(archives[0], archives[1]);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_length_limited_huffman() {
+    // fibonacci-like quantities give the deepest possible Huffman tree
+    let mut stats = [0_u64; 256];
+    let (mut a, mut b) = (1, 1);
+    for quantity in stats.iter_mut().take(20) {
+        *quantity = a;
+        (a, b) = (b, a + b);
+    }
+    let total = stats.iter().sum();
+
+    assert_eq!(
+        huffman::encode((stats, total)).values().map(Vec::len).max(),
+        Some(19)
+    );
+
+    let codes = huffman::encode_limited((stats, total), 7).unwrap();
+    assert_eq!(codes.len(), 20);
+    assert_eq!(codes.values().map(Vec::len).max(), Some(7));
+
+    // Kraft equality: limited code is still complete
+    let kraft: f64 = codes.values().map(|c| 0.5_f64.powi(c.len() as i32)).sum();
+    assert_eq!(kraft, 1.0);
+
+    // with enough room the limited code is the regular Huffman code
+    let unlimited: usize = huffman::encode((stats, total))
+        .iter()
+        .map(|(s, c)| stats[*s as usize] as usize * c.len())
+        .sum();
+    let limited: usize = huffman::encode_limited((stats, total), 19)
+        .unwrap()
+        .iter()
+        .map(|(s, c)| stats[*s as usize] as usize * c.len())
+        .sum();
+    assert_eq!(unlimited, limited);
+
+    assert!(huffman::encode_limited((stats, total), 4).is_err());
+
+    let data: Vec<u8> = (0..20_u8)
+        .flat_map(|i| vec![i; stats[i as usize] as usize])
+        .collect();
+    let decoded = roundtrip_with("limited", &data, |settings| {
+        settings.coding = Coding::Huffman {
+            max_code_length: Some(6),
+        }
+    });
+    assert_eq!(decoded, data);
+
+    // limit is checked against the file before archive is created
+    let dir = scratch_dir("limitedshort");
+    let path = dir.join("sample.txt").to_str().unwrap().to_owned();
+    fs::write(&path, &data).unwrap();
+    let err = encode_file(EncodingSettings {
+        coding: Coding::Huffman {
+            max_code_length: Some(2),
+        },
+        ..settings(&path, CodeType::Huffman)
+    })
+    .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+    assert!(!fs::exists(path + ARCHIVE_EXTENSION).unwrap());
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_crc32() {
+    assert_eq!(crc32::checksum(b""), 0);
+    assert_eq!(crc32::checksum(b"123456789"), 0xCBF43926);
+    assert_eq!(
+        crc32::checksum(b"The quick brown fox jumps over the lazy dog"),
+        0x414FA339
+    );
+
+    let mut crc = crc32::Crc32::new();
+    crc.update(b"1234");
+    crc.update(b"56789");
+    assert_eq!(crc.finalize(), 0xCBF43926);
+}
+
+#[test]
+fn test_corrupted_archive_is_detected() {
+    let dir = scratch_dir("corrupted");
+    let path = dir.join("sample.txt").to_str().unwrap().to_owned();
+    // four equally frequent symbols get 2-bit codes, so a flipped bit changes a symbol but not their count
+    fs::write(&path, b"abcd".repeat(64)).unwrap();
+
+    encode_file(settings(&path, CodeType::Huffman)).unwrap();
+
+    let archive_path = path.clone() + ARCHIVE_EXTENSION;
+    let archive = File::options()
+        .read(true)
+        .write(true)
+        .open(&archive_path)
+        .unwrap();
+    let (header, _) = ArchiveHeader::read_from(&archive).unwrap();
+    assert!(header.has_checksum);
+
+    // flip one bit in the middle of data section
+    let position = archive.metadata().unwrap().len() - 20;
+    let mut byte = [0_u8];
+    archive.read_exact_at(&mut byte, position).unwrap();
+    archive.write_at(&[byte[0] ^ 0b100], position).unwrap();
+
+    let err = decode_file(Some((File::open(&archive_path).unwrap(), archive_path))).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+    assert!(err
+        .get_ref()
+        .unwrap()
+        .downcast_ref::<ChecksumMismatchError>()
+        .is_some());
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_adaptive_huffman() {
+    let data = b"abracadabra, abracadabra! an adaptive tree follows the statistics";
+
+    let mut encoder = AdaptiveHuffman::new();
+    let mut bitmap = BitMap::new();
+    for byte in data {
+        encoder.encode(*byte, &mut bitmap);
+    }
+    let bits = bitmap.take_all_bits();
+
+    let mut decoder = AdaptiveHuffman::new();
+    let mut source = bits.iter();
+    let decoded: Vec<u8> = data
+        .iter()
+        .map(|_| decoder.decode(&mut source).unwrap())
+        .collect();
+    assert_eq!(decoded, data);
+    assert!(source.next().is_none());
+
+    // repeated symbols quickly get short codes
+    assert!(bits.len() < data.len() * 8);
+
+    let all_bytes: Vec<u8> = (0..=255).chain((0..=255).rev()).chain([7; 300]).collect();
+    assert_eq!(
+        roundtrip("adaptive", &all_bytes, CodeType::AdaptiveHuffman, None),
+        all_bytes
+    );
+    assert_eq!(
+        roundtrip("adaptivehm", data, CodeType::AdaptiveHuffman, Some(15)),
+        data
+    );
+}
+
+#[test]
+fn test_arithmetic_coding() {
+    // 'a' has probability 0.98, entropy is about 0.16 bits per symbol
+    let mut data = vec![b'a'; 4900];
+    for i in (0..4900).step_by(50) {
+        data[i] = b'b' + (i % 3) as u8;
+    }
+
+    let mut stats = [0_u64; 256];
+    for byte in &data {
+        stats[*byte as usize] += 1;
+    }
+
+    for model in [
+        FrequencyModel::from_stats(&(stats, data.len() as u64)),
+        FrequencyModel::adaptive(),
+    ] {
+        let mut encoder_model = model.clone();
+        let mut encoder = ArithmeticEncoder::new();
+        let mut bitmap = BitMap::new();
+        for byte in &data {
+            encoder.encode_symbol(&mut encoder_model, *byte, &mut bitmap);
+        }
+        encoder.finish(&mut bitmap);
+        let bits = bitmap.take_all_bits();
+
+        // well below 1 bit per symbol which is the limit of Huffman coding
+        assert!(bits.len() < data.len() / 4);
+
+        let mut decoder_model = model.clone();
+        let mut source = bits.iter();
+        let mut decoder = ArithmeticDecoder::new(&mut source).unwrap();
+        let decoded: Vec<u8> = data
+            .iter()
+            .map(|_| {
+                decoder
+                    .decode_symbol(&mut decoder_model, &mut source)
+                    .unwrap()
+            })
+            .collect();
+        assert_eq!(decoded, data);
+    }
+
+    assert_eq!(
+        roundtrip("arithmetic", &data, CodeType::Arithmetic, None),
+        data
+    );
+    assert_eq!(
+        roundtrip(
+            "arithmeticadaptive",
+            &data,
+            CodeType::AdaptiveArithmetic,
+            None
+        ),
+        data
+    );
+    assert_eq!(
+        roundtrip("arithmetichm", &data, CodeType::Arithmetic, Some(31)),
+        data
+    );
+    assert_eq!(
+        roundtrip("arithmeticempty", b"", CodeType::Arithmetic, None),
+        b""
+    );
+    assert_eq!(
+        roundtrip("arithmeticsingle", b"x", CodeType::AdaptiveArithmetic, None),
+        b"x"
+    );
+    assert_eq!(
+        decode_with_zero_frequencies("arithmeticzero", &data, CodeType::Arithmetic).kind(),
+        ErrorKind::InvalidData
+    );
+}
+
+#[test]
+fn test_ans_coding() {
+    // several blocks, the last one incomplete
+    let data: Vec<u8> = (0..ans::BLOCK_SIZE * 2 + 1000)
+        .map(|i| if i % 20 == 0 { (i % 4) as u8 } else { b'e' })
+        .collect();
+
+    let mut stats = [0_u64; 256];
+    for byte in &data {
+        stats[*byte as usize] += 1;
+    }
+
+    let table = AnsTable::from_stats(&(stats, data.len() as u64));
+    assert_eq!(
+        table.frequencies().iter().sum::<u32>(),
+        1 << ans::SCALE_BITS
+    );
+    for (frequency, quantity) in table.frequencies().iter().zip(stats) {
+        assert_eq!(*frequency == 0, quantity == 0);
+    }
+
+    let mut encoder = AnsEncoder::new(
+        AnsTable::from_frequencies(*table.frequencies(), data.len() as u64).unwrap(),
+    );
+    let mut bitmap = BitMap::new();
+    encoder.encode(&data[..5000], &mut bitmap);
+    encoder.encode(&data[5000..], &mut bitmap);
+    encoder.finish(&mut bitmap);
+    let bits = bitmap.take_all_bits();
+
+    // close to entropy (about 0.4 bits per symbol) while Huffman spends at least 1 bit
+    assert!(bits.len() < data.len() / 2);
+
+    let mut source = bits.iter();
+    let mut decoder = AnsDecoder::new(table, data.len() as u64);
+    let decoded: Vec<u8> = data
+        .iter()
+        .map(|_| decoder.decode(&mut source).unwrap())
+        .collect();
+    assert_eq!(decoded, data);
+
+    assert!(AnsTable::from_frequencies([1; 256], 256).is_err());
+    assert!(AnsTable::from_frequencies([0; 256], 0).is_ok());
+    assert!(AnsTable::from_frequencies([0; 256], 1).is_err());
+    assert_eq!(
+        decode_with_zero_frequencies("anszero", &data[..100], CodeType::Ans).kind(),
+        ErrorKind::InvalidData
+    );
+
+    assert_eq!(roundtrip("ans", &data, CodeType::Ans, None), data);
+    assert_eq!(roundtrip("anshm", &data, CodeType::Ans, Some(15)), data);
+    assert_eq!(roundtrip("ansempty", b"", CodeType::Ans, None), b"");
+    assert_eq!(roundtrip("anssingle", b"zzz", CodeType::Ans, None), b"zzz");
+}
+
+#[test]
+fn test_lzss() {
+    for distance in 1..=1 << lzss::MAX_WINDOW_BITS {
+        let (slot, extra_bits, extra) = lzss::distance_slot(distance);
+        assert!(slot < 2 * lzss::MAX_WINDOW_BITS);
+        assert_eq!(lzss::extra_bits(slot), extra_bits);
+        assert_eq!(lzss::distance_from_slot(slot, extra), distance);
+    }
+
+    let data: Vec<u8> = (0..3000)
+        .flat_map(|i| format!("{},INFO,request {} served\n", i % 17, i % 5).into_bytes())
+        .collect();
+
+    for window_bits in [lzss::MIN_WINDOW_BITS, lzss::DEFAULT_WINDOW_BITS] {
+        let mut encoder = LzssEncoder::new(window_bits).unwrap();
+        let mut tokens = vec![];
+        for (i, chunk) in data.chunks(1000).enumerate() {
+            let end_of_data = (i + 1) * 1000 >= data.len();
+            encoder.encode(chunk, end_of_data, |token| tokens.push(token));
+        }
+
+        // repeated lines collapse into few long matches
+        assert!(tokens.len() < data.len() / 10);
+        assert!(tokens.iter().all(|token| match token {
+            Token::Match { distance, .. } => *distance <= 1 << window_bits,
+            _ => true,
+        }));
+
+        let mut decoder = LzssDecoder::new(window_bits).unwrap();
+        let mut decoded = vec![];
+        for token in tokens {
+            decoder.decode(token, &mut decoded).unwrap();
+        }
+        assert_eq!(decoded, data);
+    }
+
+    assert!(LzssEncoder::new(lzss::MAX_WINDOW_BITS + 1).is_err());
+    assert!(LzssDecoder::new(8)
+        .unwrap()
+        .decode(
+            Token::Match {
+                length: 3,
+                distance: 1
+            },
+            &mut vec![]
+        )
+        .is_err());
+
+    assert_eq!(roundtrip("lzss", &data, CodeType::Lzss, None), data);
+    assert_eq!(roundtrip("lzsshm", &data, CodeType::Lzss, Some(20)), data);
+    assert_eq!(roundtrip("lzssempty", b"", CodeType::Lzss, None), b"");
+    assert_eq!(
+        roundtrip("lzsssingle", b"aaaaaaaaaa", CodeType::Lzss, None),
+        b"aaaaaaaaaa"
+    );
+
+    let decoded = roundtrip_with("lzsswindow", &data, |settings| {
+        settings.coding = Coding::Lzss {
+            window_bits: Some(lzss::MIN_WINDOW_BITS),
+        };
+    });
+    assert_eq!(decoded, data);
+}
+
+#[test]
+fn test_lzw() {
+    // "aaaa..." makes decoder meet codes of strings it has not added yet (KwKwK case)
+    let mut data = vec![b'a'; 500];
+    for i in 0..20000_u32 {
+        data.extend_from_slice(format!("{} ", i % 97).as_bytes());
+    }
+
+    let mut previous_length = data.len() * 8;
+    for max_bits in [
+        lzw::MIN_CODE_BITS,
+        lzw::DEFAULT_CODE_BITS,
+        lzw::MAX_CODE_BITS,
+    ] {
+        let mut encoder = LzwEncoder::new(max_bits).unwrap();
+        let mut bitmap = BitMap::new();
+        for chunk in data.chunks(777) {
+            encoder.encode(chunk, &mut bitmap);
+        }
+        encoder.finish(&mut bitmap);
+        let bits = bitmap.take_all_bits();
+        // larger dictionary is reset less often and finds longer strings
+        assert!(bits.len() < previous_length);
+        previous_length = bits.len();
+
+        let mut source = bits.iter();
+        let mut decoder = LzwDecoder::new(max_bits).unwrap();
+        let mut decoded = vec![];
+        while decoded.len() < data.len() {
+            decoder.decode(&mut source, &mut decoded).unwrap();
+        }
+        assert_eq!(decoded, data);
+    }
+
+    assert!(LzwEncoder::new(lzw::MAX_CODE_BITS + 1).is_err());
+
+    assert_eq!(roundtrip("lzw", &data, CodeType::Lzw, None), data);
+    assert_eq!(roundtrip("lzwhm", &data, CodeType::Lzw, Some(12)), data);
+    assert_eq!(roundtrip("lzwempty", b"", CodeType::Lzw, None), b"");
+    assert_eq!(roundtrip("lzwsingle", b"q", CodeType::Lzw, None), b"q");
+
+    let decoded = roundtrip_with("lzwreset", &data, |settings| {
+        settings.coding = Coding::Lzw {
+            max_code_bits: Some(lzw::MIN_CODE_BITS),
+        };
+    });
+    assert_eq!(decoded, data);
+
+    // parameter is checked before archive is created
+    let dir = scratch_dir("lzwinvalid");
+    let path = dir.join("sample.txt").to_str().unwrap().to_owned();
+    fs::write(&path, &data).unwrap();
+    let err = encode_file(EncodingSettings {
+        coding: Coding::Lzw {
+            max_code_bits: Some(lzw::MAX_CODE_BITS + 1),
+        },
+        ..settings(&path, CodeType::Lzw)
+    })
+    .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+    assert!(!fs::exists(path + ARCHIVE_EXTENSION).unwrap());
+    fs::remove_dir_all(&dir).unwrap();
+    let zero_length = Coding::Huffman {
+        max_code_length: Some(0),
+    };
+    assert_eq!(
+        check_coding(&zero_length).unwrap_err().kind(),
+        ErrorKind::InvalidInput
+    );
+}
+
+#[test]
+fn test_bwt() {
+    let (last_column, primary) = bwt::transform(b"banana");
+    // rows: $, a$, ana$, anana$, banana$, na$, nana$
+    assert_eq!(last_column, b"annbaa");
+    assert_eq!(primary, 4);
+
+    let mut pseudo_random = vec![];
+    let mut state = 1_u32;
+    for _ in 0..3000 {
+        state = state.wrapping_mul(1_103_515_245).wrapping_add(12345);
+        pseudo_random.push((state >> 24) as u8);
+    }
+
+    for block in [
+        b"".to_vec(),
+        b"x".to_vec(),
+        vec![b'a'; 1000],
+        b"abababababab".to_vec(),
+        pseudo_random,
+    ] {
+        let (last_column, primary) = bwt::transform(&block);
+        assert_eq!(
+            bwt::inverse_transform(&last_column, primary).unwrap(),
+            block
+        );
+
+        let values = bwt::move_to_front(&last_column);
+        assert_eq!(bwt::inverse_move_to_front(&values), last_column);
+    }
+    assert!(bwt::inverse_transform(b"abc", 4).is_err());
+
+    let text: Vec<u8> = (0..bwt::BLOCK_SIZE / 25)
+        .flat_map(|i| format!("the line number {} of the text\n", i % 300).into_bytes())
+        .collect();
+    assert!(text.len() > bwt::BLOCK_SIZE);
+
+    // similar contexts give long runs of zeros
+    let (_, symbols) = bwt::encode_block(&text[..10000]);
+    assert!(symbols.len() < 10000 / 4);
+    assert!(symbols.iter().any(|s| matches!(s, Symbol::ZeroRun(_))));
+
+    assert_eq!(roundtrip("bwt", &text, CodeType::Bwt, None), text);
+    assert_eq!(
+        roundtrip("bwthm", &text[..5000], CodeType::Bwt, Some(9)),
+        &text[..5000]
+    );
+    assert_eq!(roundtrip("bwtempty", b"", CodeType::Bwt, None), b"");
+}
+
+#[test]
+fn test_context_huffman() {
+    // every letter is almost always followed by the same one, while order-0 statistics are flat
+    let mut data: Vec<u8> = b"abcdefgh".repeat(2000);
+    data.extend_from_slice(b"xyz!");
+
+    let mut stats = ContextStats::new();
+    stats.update(&data[..100]);
+    stats.update(&data[100..]);
+    assert_eq!(stats.quantities(b'a')[b'b' as usize], 2000);
+
+    let codes = ContextCodes::build(&stats);
+    assert!(codes.own[b'a' as usize].is_some());
+    // contexts seen once are not worth a table of their own
+    assert!(codes.own[b'x' as usize].is_none());
+    assert!(codes.own[b'!' as usize].is_none());
+
+    let mut bitmap = BitMap::new();
+    let mut previous = context::INITIAL_CONTEXT;
+    for byte in &data {
+        bitmap.add_bit_sequence(codes.code(previous, *byte));
+        previous = *byte;
+    }
+    let bits = bitmap.take_all_bits();
+
+    let mut order_0_stats = [0_u64; 256];
+    for byte in &data {
+        order_0_stats[*byte as usize] += 1;
+    }
+    let order_0 = huffman::encode((order_0_stats, data.len() as u64));
+    let order_0_length: usize = data.iter().map(|byte| order_0[byte].len()).sum();
+    assert!(bits.len() < order_0_length / 2);
+
+    let lengths = |codes: &HashMap<u8, Vec<u8>>| -> HashMap<u8, usize> {
+        codes.iter().map(|(s, code)| (*s, code.len())).collect()
+    };
+    let own: Vec<Option<HashMap<u8, usize>>> = codes
+        .own
+        .iter()
+        .map(|codes| codes.as_ref().map(lengths))
+        .collect();
+    let mut decoder = ContextDecoder::new(&lengths(&codes.shared), &own);
+    let mut source = bits.iter();
+    let decoded: Vec<u8> = data
+        .iter()
+        .map(|_| decoder.decode(&mut source).unwrap())
+        .collect();
+    assert_eq!(decoded, data);
+
+    assert_eq!(
+        roundtrip("context", &data, CodeType::ContextHuffman, None),
+        data
+    );
+    assert_eq!(
+        roundtrip("contexthm", &data, CodeType::ContextHuffman, Some(7)),
+        data
+    );
+    assert_eq!(
+        roundtrip("contextempty", b"", CodeType::ContextHuffman, None),
+        b""
+    );
+    assert_eq!(
+        roundtrip("contextsingle", b"\0", CodeType::ContextHuffman, None),
+        b"\0"
+    );
+}
+
+#[test]
+fn test_ppm() {
+    let data: Vec<u8> = (0..400)
+        .flat_map(|i| {
+            format!("the {} quick brown fox jumps over the lazy dog. ", i % 7).into_bytes()
+        })
+        .collect();
+
+    let mut stats = [0_u64; 256];
+    for byte in &data {
+        stats[*byte as usize] += 1;
+    }
+    let order_0 = huffman::encode((stats, data.len() as u64));
+    let order_0_length: usize = data.iter().map(|byte| order_0[byte].len()).sum();
+
+    for order in [ppm::MIN_ORDER, ppm::DEFAULT_ORDER, ppm::MAX_ORDER] {
+        let mut model = PpmModel::new(order).unwrap();
+        let mut encoder = ArithmeticEncoder::new();
+        let mut bitmap = BitMap::new();
+        for byte in &data {
+            model.encode(*byte, &mut encoder, &mut bitmap);
+        }
+        encoder.finish(&mut bitmap);
+        let bits = bitmap.take_all_bits();
+
+        // higher order contexts predict repeated phrases almost for free
+        assert!(bits.len() < order_0_length / 3);
+        let estimate = PpmModel::new(order).unwrap().code_length(&data);
+        assert!((bits.len() as f64 - estimate).abs() < 64.0);
+
+        let mut model = PpmModel::new(order).unwrap();
+        let mut source = bits.iter();
+        let mut decoder = ArithmeticDecoder::new(&mut source).unwrap();
+        let decoded: Vec<u8> = data
+            .iter()
+            .map(|_| model.decode(&mut decoder, &mut source).unwrap())
+            .collect();
+        assert_eq!(decoded, data);
+    }
+
+    assert!(PpmModel::new(ppm::MAX_ORDER + 1).is_err());
+
+    assert_eq!(roundtrip("ppm", &data, CodeType::Ppm, None), data);
+    assert_eq!(roundtrip("ppmhm", &data, CodeType::Ppm, Some(63)), data);
+    assert_eq!(roundtrip("ppmempty", b"", CodeType::Ppm, None), b"");
+
+    let binary: Vec<u8> = (0..=255).chain((0..=255).rev()).collect();
+    let decoded = roundtrip_with("ppmorder", &binary, |settings| {
+        settings.coding = Coding::Ppm {
+            order: Some(ppm::MAX_ORDER),
+        };
+    });
+    assert_eq!(decoded, binary);
+}
+
+#[test]
+fn test_shannon_codes() {
+    let stats = sample_stats();
+    let average = |codes: &HashMap<u8, Vec<u8>>| average_length(codes, &stats);
+    let entropy = entropy(&stats);
+
+    let shannon = shannon::encode(stats);
+    let elias = shannon_fano_elias::encode(stats);
+    let huffman = huffman::encode(stats);
+
+    // cumulative probability of 'a' is 0 and it takes ceil(-log2 0.45) = 2 bits
+    assert_eq!(shannon[&b'a'], vec![0, 0]);
+    // middle of the first interval is 0.225, so 3 bits 001
+    assert_eq!(elias[&b'a'], vec![0, 0, 1]);
+
+    for codes in [&shannon, &elias] {
+        assert_eq!(codes.len(), 6);
+        for (a, code_a) in codes.iter() {
+            for (b, code_b) in codes.iter() {
+                assert!(a == b || !code_b.starts_with(code_a));
+            }
+        }
+    }
+
+    assert!(average(&huffman) <= average(&shannon));
+    assert!(average(&shannon) < entropy + 1.0);
+    assert!(average(&elias) < entropy + 2.0);
+
+    let single = single_symbol_stats();
+    assert_eq!(shannon::encode(single)[&7].len(), 1);
+    assert_eq!(shannon_fano_elias::encode(single)[&7].len(), 1);
+
+    let data = sample_data();
+    assert_eq!(roundtrip("shannon", &data, CodeType::Shannon, None), data);
+    assert_eq!(
+        roundtrip("elias", &data, CodeType::ShannonFanoElias, Some(9)),
+        data
+    );
+}
+
+#[test]
+fn test_d_ary_huffman() {
+    let stats = sample_stats();
+    let average = |codes: &HashMap<u8, Vec<u8>>| average_length(codes, &stats);
+    let entropy = entropy(&stats);
+
+    assert_eq!(
+        average(&huffman::encode_d_ary(stats, 2)),
+        average(&huffman::encode(stats))
+    );
+
+    for arity in [3_u8, 4, 5] {
+        let codes = huffman::encode_d_ary(stats, arity);
+        assert_eq!(codes.len(), 6);
+
+        let kraft: f64 = codes
+            .values()
+            .map(|code| (arity as f64).powi(-(code.len() as i32)))
+            .sum();
+        assert!(kraft <= 1.0);
+
+        for (a, code_a) in codes.iter() {
+            assert!(code_a.iter().all(|digit| *digit < arity));
+            for (b, code_b) in codes.iter() {
+                assert!(a == b || !code_b.starts_with(code_a));
+            }
+        }
+
+        let entropy = entropy / (arity as f64).log2();
+        assert!(average(&codes) >= entropy && average(&codes) < entropy + 1.0);
+
+        let lengths = codes.iter().map(|(s, code)| (*s, code.len())).collect();
+        let canonical_codes = canonical::assign_d_ary_codes(&lengths, arity);
+        let mut decoder = CanonicalDecoder::with_arity(&lengths, arity);
+        for (symbol, code) in &canonical_codes {
+            assert_eq!(code.len(), codes[symbol].len());
+            let (last, rest) = code.split_last().unwrap();
+            for digit in rest {
+                assert_eq!(decoder.push_digit(*digit).unwrap(), None);
+            }
+            assert_eq!(decoder.push_digit(*last).unwrap(), Some(*symbol));
+        }
+    }
+
+    // ternary code with 6 symbols needs one dummy leaf, 'a' gets a single digit
+    assert_eq!(huffman::encode_d_ary(stats, 3)[&b'a'].len(), 1);
+
+    assert_eq!(huffman::digit_packing(4), (1, 2));
+    assert_eq!(huffman::digit_packing(3), (5, 8));
+    assert_eq!(huffman::digit_packing(255), (1, 8));
+    assert!(huffman::check_arity(1).is_err());
+
+    let data = sample_data();
+    for (arity, hamming_code_length) in [(3, None), (5, Some(9))] {
+        let decoded = roundtrip_with("dary", &data, |settings| {
+            settings.coding = Coding::DaryHuffman { arity: Some(arity) };
+            settings.error_correction =
+                hamming_code_length.map_or(ErrorCorrection::None, hamming_code);
+        });
+        assert_eq!(decoded, data);
+    }
+    assert_eq!(
+        roundtrip("darysingle", &[9; 100], CodeType::DaryHuffman, None),
+        vec![9; 100]
+    );
+}
+
+#[test]
+fn test_hu_tucker() {
+    let mut quantities = [0_u64; 256];
+    let weights = [25, 1, 3, 40, 7, 7, 2, 60, 5, 11, 1, 30];
+    for (i, quantity) in weights.iter().enumerate() {
+        quantities[b'a' as usize + 2 * i] = *quantity;
+    }
+    let total = weights.iter().sum::<u64>();
+    let codes = hu_tucker::encode((quantities, total));
+    assert_eq!(codes.len(), weights.len());
+
+    // sorted bytes keep their order after coding, and codes are prefix-free
+    let mut symbols: Vec<&u8> = codes.keys().collect();
+    symbols.sort();
+    for pair in symbols.windows(2) {
+        assert!(codes[pair[0]] < codes[pair[1]]);
+        assert!(!codes[pair[1]].starts_with(&codes[pair[0]]));
+    }
+
+    // cost of optimal alphabetic tree over weights i..=j, found by dynamic programming
+    let n = weights.len();
+    let mut cost = vec![vec![0_u64; n]; n];
+    for width in 1..n {
+        for i in 0..n - width {
+            let j = i + width;
+            let sum: u64 = weights[i..=j].iter().sum();
+            cost[i][j] = (i..j).map(|k| cost[i][k] + cost[k + 1][j]).min().unwrap() + sum;
+        }
+    }
+    let code_length: u64 = codes
+        .iter()
+        .map(|(symbol, code)| quantities[*symbol as usize] * code.len() as u64)
+        .sum();
+    assert_eq!(code_length, cost[0][n - 1]);
+
+    let huffman_length: u64 = huffman::encode((quantities, total))
+        .iter()
+        .map(|(symbol, code)| quantities[*symbol as usize] * code.len() as u64)
+        .sum();
+    assert!(huffman_length <= code_length);
+
+    let lengths = codes.iter().map(|(s, code)| (*s, code.len())).collect();
+    assert_eq!(hu_tucker::assign_codes(&lengths).unwrap(), codes);
+    let invalid = HashMap::from([(1, 1), (2, 2), (3, 1)]);
+    assert!(hu_tucker::assign_codes(&invalid).is_err());
+
+    let single = single_symbol_stats();
+    assert_eq!(hu_tucker::encode(single)[&7], vec![0]);
+
+    let data = sample_data();
+    assert_eq!(roundtrip("hutucker", &data, CodeType::HuTucker, None), data);
+    assert_eq!(
+        roundtrip("hutuckerecc", &data, CodeType::HuTucker, Some(9)),
+        data
+    );
+}
+
+#[test]
+fn test_tunstall() {
+    // 'a' - 85%, 'b' - 10%, 'c' - 3%, 'd' - 2%, drawn by linear congruential generator
+    let mut state = 1_u32;
+    let data: Vec<u8> = (0..20000)
+        .map(|_| {
+            state = state.wrapping_mul(1103515245).wrapping_add(12345);
+            match (state >> 16) % 100 {
+                0..=84 => b'a',
+                85..=94 => b'b',
+                95..=97 => b'c',
+                _ => b'd',
+            }
+        })
+        .collect();
+    let mut stats = ([0_u64; 256], data.len() as u64);
+    for byte in &data {
+        stats.0[*byte as usize] += 1;
+    }
+    let frequencies = tunstall::scale_frequencies(&stats);
+
+    let mut previous_length = usize::MAX;
+    for code_bits in [8, 12, 16] {
+        let code = TunstallCode::new(&frequencies, code_bits).unwrap();
+        // every expansion adds 3 leaves, so at most 2 indices stay unused
+        assert!(code.phrase_count() <= 1 << code_bits);
+        assert!(code.phrase_count() + 2 >= 1 << code_bits);
+
+        // phrases follow in lexicographic order and none is a prefix of another
+        let phrases: Vec<Vec<u8>> = (0..code.phrase_count())
+            .map(|index| {
+                let mut bits = BitMap::new();
+                bits.add_value(index as u64, code_bits as usize);
+                let bits = bits.take_all_bits();
+                let mut phrase = vec![];
+                code.decode(&mut bits.iter(), &mut phrase).unwrap();
+                phrase
+            })
+            .collect();
+        for pair in phrases.windows(2) {
+            assert!(pair[0] < pair[1]);
+            assert!(!pair[1].starts_with(&pair[0]));
+        }
+
+        let mut bits = BitMap::new();
+        let mut coder = TunstallEncoder::new(code);
+        coder.encode(&data, &mut bits);
+        coder.finish(&mut bits);
+        let bits = bits.take_all_bits();
+
+        // entropy of the source is about 0.8 bits
+        assert!(bits.len() < data.len());
+        assert!(bits.len() < previous_length);
+        previous_length = bits.len();
+
+        let code = TunstallCode::new(&frequencies, code_bits).unwrap();
+        let mut decoded = vec![];
+        let mut source = bits.iter();
+        while decoded.len() < data.len() {
+            code.decode(&mut source, &mut decoded).unwrap();
+        }
+        decoded.truncate(data.len());
+        assert_eq!(decoded, data);
+    }
+
+    assert!(tunstall::check_code_bits(7).is_err());
+
+    let decoded = roundtrip_with("tunstall", &data, |settings| {
+        settings.coding = Coding::Tunstall {
+            code_bits: Some(10),
+        };
+        settings.error_correction = hamming_code(9);
+    });
+    assert_eq!(decoded, data);
+    // phrases of a single symbol are as long as the dictionary allows, the tail is cut by decoder
+    assert_eq!(
+        roundtrip("tunstallsingle", &[5; 10000], CodeType::Tunstall, None),
+        vec![5; 10000]
+    );
+}
+
+#[test]
+fn test_universal_codes() {
+    let code_of = |encode: &dyn Fn(&mut BitMap)| -> String {
+        let mut bits = BitMap::new();
+        encode(&mut bits);
+        bits.take_all_bits()
+            .iter()
+            .map(|bit| bit.to_string())
+            .collect()
+    };
+
+    assert_eq!(code_of(&|bits| universal::encode_gamma(1, bits)), "1");
+    assert_eq!(code_of(&|bits| universal::encode_gamma(5, bits)), "00101");
+    assert_eq!(code_of(&|bits| universal::encode_delta(5, bits)), "01101");
+    assert_eq!(code_of(&|bits| universal::encode_omega(1, bits)), "0");
+    assert_eq!(code_of(&|bits| universal::encode_omega(5, bits)), "101010");
+    assert_eq!(
+        code_of(&|bits| universal::encode_fibonacci(5, bits)),
+        "00011"
+    );
+    assert_eq!(
+        code_of(&|bits| universal::encode_fibonacci(12, bits)),
+        "101011"
+    );
+    assert_eq!(
+        code_of(&|bits| universal::encode_golomb(42, 10, bits)),
+        "11110010"
+    );
+    assert_eq!(
+        code_of(&|bits| universal::encode_golomb(49, 10, bits)),
+        "111101111"
+    );
+    assert_eq!(code_of(&|bits| universal::encode_rice(9, 2, bits)), "11001");
+    assert_eq!(
+        code_of(&|bits| universal::encode_exp_golomb(0, 0, bits)),
+        "1"
+    );
+    assert_eq!(
+        code_of(&|bits| universal::encode_exp_golomb(3, 0, bits)),
+        "00100"
+    );
+    assert_eq!(
+        code_of(&|bits| universal::encode_exp_golomb(3, 1, bits)),
+        "0101"
+    );
+
+    let values: Vec<u64> = (1..300)
+        .chain([1 << 20, (1 << 32) + 7, u64::MAX / 3, u64::MAX - 1, u64::MAX])
+        .collect();
+    let mut bits = BitMap::new();
+    for value in &values {
+        universal::encode_gamma(*value, &mut bits);
+        universal::encode_delta(*value, &mut bits);
+        universal::encode_omega(*value, &mut bits);
+        universal::encode_fibonacci(*value, &mut bits);
+        universal::encode_exp_golomb(*value, 0, &mut bits);
+        universal::encode_exp_golomb(*value, 5, &mut bits);
+        assert_eq!(
+            universal::exp_golomb_length(*value, 5),
+            code_of(&|bits| universal::encode_exp_golomb(*value, 5, bits)).len()
+        );
+        // unary quotient of Golomb codes stays short for small values only
+        if *value < 300 {
+            universal::encode_golomb(*value, 7, &mut bits);
+            universal::encode_golomb(*value - 1, 1, &mut bits);
+            universal::encode_rice(*value, 3, &mut bits);
+        }
+    }
+    let bits = bits.take_all_bits();
+
+    let mut source = bits.iter();
+    for value in &values {
+        assert_eq!(universal::decode_gamma(&mut source).unwrap(), *value);
+        assert_eq!(universal::decode_delta(&mut source).unwrap(), *value);
+        assert_eq!(universal::decode_omega(&mut source).unwrap(), *value);
+        assert_eq!(universal::decode_fibonacci(&mut source).unwrap(), *value);
+        assert_eq!(
+            universal::decode_exp_golomb(0, &mut source).unwrap(),
+            *value
+        );
+        assert_eq!(
+            universal::decode_exp_golomb(5, &mut source).unwrap(),
+            *value
+        );
+        if *value < 300 {
+            assert_eq!(universal::decode_golomb(7, &mut source).unwrap(), *value);
+            assert_eq!(
+                universal::decode_golomb(1, &mut source).unwrap(),
+                *value - 1
+            );
+            assert_eq!(universal::decode_rice(3, &mut source).unwrap(), *value);
+        }
+    }
+    assert!(source.next().is_none());
+
+    // 65 zeros cannot start a code of 64-bit value
+    let zeros = [0_u8; 80];
+    assert_eq!(
+        universal::decode_gamma(&mut zeros.iter())
+            .unwrap_err()
+            .kind(),
+        ErrorKind::InvalidData
+    );
+}
+
+#[test]
+fn test_ngram_huffman() {
+    assert_eq!(ngram::join(&[1, 2, 3]), 0x010203);
+    assert_eq!(ngram::split(0x010203, 3), vec![1, 2, 3]);
+    assert!(ngram::check_length(4).is_err());
+
+    // general Huffman lengths are those of the byte code
+    let mut quantities = [0_u64; 256];
+    for (byte, quantity) in [(1, 45), (2, 13), (3, 12), (4, 16), (5, 9), (6, 5)] {
+        quantities[byte] = quantity;
+    }
+    let lengths = huffman::code_lengths(&quantities);
+    let codes = huffman::encode((quantities, 100));
+    for (symbol, length) in lengths.iter().enumerate() {
+        assert_eq!(*length, codes.get(&(symbol as u8)).map_or(0, Vec::len));
+    }
+    assert_eq!(huffman::code_lengths(&[0, 7, 0]), vec![0, 1, 0]);
+
+    // bytes are independent, so a block of k bytes is at most 1 / k bits per byte over entropy
+    let mut state = 7_u32;
+    let data: Vec<u8> = (0..30001)
+        .map(|_| {
+            state = state.wrapping_mul(1103515245).wrapping_add(12345);
+            match (state >> 16) % 10 {
+                0..=6 => b'a',
+                7..=8 => b'b',
+                _ => b'c',
+            }
+        })
+        .collect();
+    let entropy = -(0.7_f64 * 0.7_f64.log2() + 0.2 * 0.2_f64.log2() + 0.1 * 0.1_f64.log2());
+
+    // code of single bytes is 1, 2 and 2 bits long
+    let single_byte_bits = 0.7 * 1.0 + 0.2 * 2.0 + 0.1 * 2.0;
+    for length in [2, 3] {
+        let mut quantities: HashMap<u32, u64> = HashMap::new();
+        let mut ngrams = Ngrams::new(length);
+        ngrams.push(&data[..1000], |ngram| {
+            *quantities.entry(ngram).or_insert(0) += 1
+        });
+        ngrams.push(&data[1000..], |ngram| {
+            *quantities.entry(ngram).or_insert(0) += 1
+        });
+        assert_eq!(
+            ngrams.tail(),
+            &data[data.len() - data.len() % length as usize..]
+        );
+
+        let symbols: Vec<u32> = quantities.keys().copied().collect();
+        let counts: Vec<u64> = symbols.iter().map(|s| quantities[s]).collect();
+        let code_bits: u64 = huffman::code_lengths(&counts)
+            .iter()
+            .zip(&counts)
+            .map(|(length, count)| *length as u64 * count)
+            .sum();
+        let bits_per_byte =
+            code_bits as f64 / (data.len() / length as usize * length as usize) as f64;
+
+        assert!(bits_per_byte < entropy + 1.0 / length as f64 + 0.01);
+        assert!(bits_per_byte < single_byte_bits);
+
+        let decoded = roundtrip_with("ngram", &data, |settings| {
+            settings.coding = Coding::NgramHuffman {
+                length: Some(length),
+            };
+            settings.error_correction = hamming_code(9);
+        });
+        assert_eq!(decoded, data);
+    }
+
+    // the whole file is a tail
+    let decoded = roundtrip_with("ngramtail", b"ab", |settings| {
+        settings.coding = Coding::NgramHuffman { length: Some(3) };
+    });
+    assert_eq!(decoded, b"ab");
+}
+
+#[test]
+fn test_words_huffman() {
+    let tokenize = |chunks: &[&[u8]]| {
+        let mut tokens: Vec<Vec<u8>> = vec![];
+        let mut tokenizer = Tokenizer::new();
+        for (i, chunk) in chunks.iter().enumerate() {
+            tokenizer.push(chunk, i == chunks.len() - 1, |token| {
+                tokens.push(token.to_vec())
+            });
+        }
+        tokens
+    };
+    let expected: Vec<Vec<u8>> = vec![
+        b"Hello".to_vec(),
+        b", ".to_vec(),
+        b"world".to_vec(),
+        b"!!".to_vec(),
+    ];
+    assert_eq!(tokenize(&[b"Hello, world!!"]), expected);
+    // tokens that cross chunk boundaries are joined
+    assert_eq!(tokenize(&[b"Hel", b"lo,", b" world!", b"!"]), expected);
+    // long runs are cut so that the length of a token fits a byte
+    let lengths: Vec<usize> = tokenize(&[&[b' '; 600]]).iter().map(|t| t.len()).collect();
+    assert_eq!(lengths, vec![255, 255, 90]);
+
+    let text: Vec<u8> = (0..2000)
+        .map(|i| ["the ", "quick ", "brown ", "fox, ", "jumps\n", "über "][i * 7 % 6])
+        .collect::<String>()
+        .into_bytes();
+
+    // codes of tokens are decoded back into the same text
+    let mut quantities: HashMap<Vec<u8>, u64> = HashMap::new();
+    for token in tokenize(&[&text]) {
+        *quantities.entry(token).or_insert(0) += 1;
+    }
+    let code = WordCode::build(&quantities);
+    // tokens get lengths of the Huffman tree built over their quantities
+    let mut tokens: Vec<&Vec<u8>> = quantities.keys().collect();
+    tokens.sort();
+    let tree_codes =
+        huffman::encode_indexed(&tokens.iter().map(|t| quantities[*t]).collect::<Vec<_>>());
+    for (id, token) in tokens.iter().enumerate() {
+        assert_eq!(code.code(token).len(), tree_codes[&id].len());
+    }
+    let mut bits = vec![];
+    for token in tokenize(&[&text]) {
+        bits.extend_from_slice(code.code(&token));
+    }
+    let mut decoder = WordDecoder::new(code.tokens.clone(), &code.length_counts());
+    let mut decoded = vec![];
+    let mut source = bits.iter();
+    while decoded.len() < text.len() {
+        decoder.decode(&mut source, &mut decoded).unwrap();
+    }
+    assert_eq!(decoded, text);
+
+    // repeated words take less than bytes coded one by one
+    let mut byte_quantities = [0_u64; 256];
+    for byte in &text {
+        byte_quantities[*byte as usize] += 1;
+    }
+    let byte_codes = huffman::encode((byte_quantities, text.len() as u64));
+    let byte_bits: usize = text.iter().map(|b| byte_codes[b].len()).sum();
+    assert!(bits.len() * 2 < byte_bits);
+
+    for (name, data) in [
+        ("words", text.clone()),
+        (
+            "wordsbinary",
+            (0..3000).map(|i| (i * i % 251) as u8).collect(),
+        ),
+        ("wordsempty", vec![]),
+    ] {
+        let decoded = roundtrip(name, &data, CodeType::WordHuffman, Some(9));
+        assert_eq!(decoded, data);
+    }
+}
+
+#[test]
+fn test_extended_hamming() {
+    assert!(hamming::check_code_length(8, false).is_err());
+    assert!(hamming::check_code_length(9, true).is_err());
+    assert!(hamming::check_code_length(7, true).is_err());
+    assert!(hamming::check_code_length(8, true).is_ok());
+    assert!(hamming::check_code_length(13, false).is_ok());
+
+    let data = vec![1, 0, 1, 1, 0, 0, 1, 0, 1];
+    let message = hamming::add_extended_parity(&data);
+    assert_eq!(message.len(), 14);
+
+    // every single error is corrected and its position is reported
+    for position in 0..message.len() {
+        let mut damaged = message.clone();
+        damaged[position] ^= 1;
+        assert_eq!(
+            hamming::remove_extended_parity(&mut damaged),
+            (data.clone(), hamming::BlockStatus::Corrected(position))
+        );
+    }
+
+    // every double error is detected, while plain hamming code corrupts data or gives up
+    for first in 0..message.len() {
+        for second in first + 1..message.len() {
+            let mut damaged = message.clone();
+            damaged[first] ^= 1;
+            damaged[second] ^= 1;
+            let (_, status) = hamming::remove_extended_parity(&mut damaged);
+            assert_eq!(status, hamming::BlockStatus::Uncorrectable);
+        }
+    }
+    let mut damaged = hamming::add_parity(&data);
+    damaged[0] ^= 1;
+    damaged[5] ^= 1;
+    assert_ne!(hamming::remove_parity(&mut damaged), data);
+    // positions 8 and 7 (counted from the end) give syndrome 15, outside of block of 13 bits
+    let mut damaged = hamming::add_parity(&data);
+    damaged[13 - 8] ^= 1;
+    damaged[13 - 7] ^= 1;
+    let received = damaged.clone();
+    let (_, report) = hamming::remove_parity_package(&mut damaged, 13, false);
+    assert_eq!(
+        report,
+        hamming::ParityReport {
+            blocks_checked: 1,
+            corrected: vec![],
+            uncorrectable: vec![0],
+        }
+    );
+    assert_eq!(damaged, received);
+
+    let package: Vec<u8> = (0..36).map(|i| (i * 7 % 5 % 2) as u8).collect();
+    let mut encoded = hamming::add_parity_package(&package, 9, true);
+    assert_eq!(encoded.len(), 4 * 14);
+    encoded[14 + 3] ^= 1;
+    encoded[3 * 14 + 1] ^= 1;
+    encoded[3 * 14 + 8] ^= 1;
+    let (decoded, report) = hamming::remove_parity_package(&mut encoded, 14, true);
+    assert_eq!(decoded[..27], package[..27]);
+    assert_eq!(
+        report,
+        hamming::ParityReport {
+            blocks_checked: 4,
+            corrected: vec![(1, 3)],
+            uncorrectable: vec![3],
+        }
+    );
+
+    let data = b"single errors in extended hamming blocks are corrected".repeat(20);
+    let decoded = roundtrip_with("secded", &data, |settings| {
+        settings.error_correction = ErrorCorrection::Hamming {
+            code_length: 16,
+            extended: true,
+        };
+    });
+    assert_eq!(decoded, data);
+
+    // plain hamming block of 16 bits would waste its last parity bit
+    let dir = scratch_dir("hamming16");
+    let path = dir.join("sample.txt").to_str().unwrap().to_owned();
+    fs::write(&path, &data).unwrap();
+    let settings = EncodingSettings {
+        error_correction: hamming_code(16),
+        ..settings(&path, CodeType::Huffman)
+    };
+    assert_eq!(
+        encode_file(settings).unwrap_err().kind(),
+        ErrorKind::InvalidInput
+    );
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_reed_solomon() {
+    assert!(reed_solomon::check_parameters(10, 10).is_err());
+    assert!(reed_solomon::check_parameters(10, 0).is_err());
+
+    let mut state = 11_u32;
+    let mut random = move |bound: usize| {
+        state = state.wrapping_mul(1103515245).wrapping_add(12345);
+        (state >> 16) as usize % bound
+    };
+
+    let code = ReedSolomon::new(255, 223).unwrap();
+    let data: Vec<u8> = (0..223).map(|_| random(256) as u8).collect();
+    let block = code.encode(&data);
+    assert_eq!(block.len(), 255);
+    assert_eq!(block[..223], data[..]);
+
+    // damaged positions of a block, `count` distinct ones
+    let mut positions = |count: usize, length: usize| {
+        let mut positions: Vec<usize> = vec![];
+        while positions.len() < count {
+            let position = random(length);
+            if !positions.contains(&position) {
+                positions.push(position);
+            }
+        }
+        positions.sort();
+        positions
+    };
+
+    // up to (n - k) / 2 errors of any value are corrected
+    let errors = positions(16, 255);
+    let mut damaged = block.clone();
+    for position in &errors {
+        damaged[*position] ^= 0x5a;
+    }
+    assert_eq!(code.decode(&mut damaged, &[]).unwrap(), errors);
+    assert_eq!(damaged, block);
+
+    let mut damaged = block.clone();
+    for position in positions(17, 255) {
+        damaged[position] ^= 0xff;
+    }
+    let copy = damaged.clone();
+    assert_eq!(
+        code.decode(&mut damaged, &[]).unwrap_err().kind(),
+        ErrorKind::InvalidData
+    );
+    assert_eq!(damaged, copy);
+
+    // erasure takes one parity byte, error takes two
+    let damaged_positions = positions(22, 255);
+    let mut damaged = block.clone();
+    for position in &damaged_positions {
+        damaged[*position] = 0;
+    }
+    let (erasures, errors) = damaged_positions.split_at(12);
+    assert_eq!(erasures.len() + 2 * errors.len(), 32);
+    code.decode(&mut damaged, erasures).unwrap();
+    assert_eq!(damaged, block);
+
+    let mut damaged = block.clone();
+    for position in positions(32, 255).iter() {
+        damaged[*position] ^= 1;
+    }
+    let erasures: Vec<usize> = (0..255).filter(|i| damaged[*i] != block[*i]).collect();
+    code.decode(&mut damaged, &erasures).unwrap();
+    assert_eq!(damaged, block);
+
+    // shortened code
+    let code = ReedSolomon::new(20, 12).unwrap();
+    let package: Vec<u8> = (0..30).collect();
+    let mut encoded = code.encode_package(&package);
+    assert_eq!(encoded.len(), 60);
+    for position in [0, 7, 13, 19, 25, 45, 46, 47, 48, 49] {
+        encoded[position] ^= 0x81;
+    }
+    let (decoded, report) = code.decode_package(&mut encoded);
+    assert_eq!(decoded[..24], package[..24]);
+    assert_eq!(
+        report,
+        hamming::ParityReport {
+            blocks_checked: 3,
+            corrected: vec![(0, 0), (0, 7), (0, 13), (0, 19), (1, 5)],
+            uncorrectable: vec![2],
+        }
+    );
+
+    // a burst of damaged bytes in archive is corrected by Reed-Solomon, but not by Hamming code
+    let text = b"burst errors spoil neighbouring bytes of the archive; ".repeat(40);
+    for (error_correction, survives) in [
+        (ErrorCorrection::ReedSolomon { n: 255, k: 223 }, true),
+        (hamming_code(9), false),
+    ] {
+        let dir = scratch_dir("burst");
+        let path = dir.join("sample.txt").to_str().unwrap().to_owned();
+        fs::write(&path, &text).unwrap();
+        let settings = EncodingSettings {
+            error_correction,
+            ..settings(&path, CodeType::Huffman)
+        };
+        encode_file(settings).unwrap();
+
+        let archive_path = path.clone() + ARCHIVE_EXTENSION;
+        let archive = File::options()
+            .read(true)
+            .write(true)
+            .open(&archive_path)
+            .unwrap();
+        let position = archive.metadata().unwrap().len() - 200;
+        archive.write_at(&[0x55; 12], position).unwrap();
+
+        let result = decode_file(Some((File::open(&archive_path).unwrap(), archive_path)));
+        assert_eq!(result.is_ok(), survives);
+        if survives {
+            assert_eq!(fs::read(dir.join("sample_1.txt")).unwrap(), text);
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    let decoded = roundtrip_with("reedsolomon", &text, |settings| {
+        settings.coding = Coding::from(CodeType::Lzw);
+        settings.error_correction = ErrorCorrection::ReedSolomon { n: 40, k: 30 };
+    });
+    assert_eq!(decoded, text);
+}
+
+#[test]
+fn test_bch() {
+    assert!(bch::check_parameters(16, 2).is_err());
+    assert!(bch::check_parameters(15, 0).is_err());
+    assert!(bch::check_parameters(15, 8).is_err());
+
+    // well-known codes: BCH(15, 7) with generator x^8 + x^7 + x^6 + x^4 + 1, BCH(15, 5), BCH(31, 16)
+    let code = Bch::new(15, 2).unwrap();
+    assert_eq!(code.data_length(), 7);
+    assert_eq!(code.generator(), &[1, 1, 1, 0, 1, 0, 0, 0, 1]);
+    assert_eq!(Bch::new(15, 3).unwrap().data_length(), 5);
+    assert_eq!(Bch::new(31, 3).unwrap().data_length(), 16);
+    // with t = 1 it is Hamming code
+    assert_eq!(
+        Bch::new(63, 1).unwrap().data_length(),
+        hamming::data_length(63)
+    );
+    assert_eq!(Bch::new(1023, 10).unwrap().data_length(), 923);
+
+    // every pattern of up to t errors is corrected
+    let data = vec![1, 0, 1, 1, 0, 0, 1];
+    let block = code.encode(&data);
+    assert_eq!(block[..7], data[..]);
+    for first in 0..15 {
+        for second in first..15 {
+            let mut damaged = block.clone();
+            damaged[first] ^= 1;
+            if second != first {
+                damaged[second] ^= 1;
+            }
+            let mut expected = vec![first, second];
+            expected.dedup();
+            assert_eq!(code.decode(&mut damaged).unwrap(), expected);
+            assert_eq!(damaged, block);
+        }
+    }
+
+    let code = Bch::new(255, 8).unwrap();
+    let mut state = 5_u32;
+    let mut random = move |bound: usize| {
+        state = state.wrapping_mul(1103515245).wrapping_add(12345);
+        (state >> 16) as usize % bound
+    };
+    let package: Vec<u8> = (0..3 * code.data_length())
+        .map(|_| random(2) as u8)
+        .collect();
+    let mut encoded = code.add_parity_package(&package);
+    assert_eq!(encoded.len(), 3 * 255);
+
+    let mut errors = vec![];
+    while errors.len() < 8 {
+        let position = random(255);
+        if !errors.contains(&position) {
+            errors.push(position);
+        }
+    }
+    errors.sort();
+    for position in &errors {
+        encoded[255 + position] ^= 1;
+    }
+    let (decoded, report) = code.remove_parity_package(&mut encoded);
+    assert_eq!(decoded, package);
+    assert_eq!(report.blocks_checked, 3);
+    assert_eq!(
+        report.corrected,
+        errors
+            .iter()
+            .map(|position| (1, *position))
+            .collect::<Vec<_>>()
+    );
+    assert!(report.uncorrectable.is_empty());
+}
+
+#[test]
+fn test_convolutional() {
+    assert!(convolutional::check_parameters(1, &[1, 1], None).is_err());
+    assert!(convolutional::check_parameters(3, &[0o7], None).is_err());
+    assert!(convolutional::check_parameters(3, &[0o7, 0o17], None).is_err());
+    assert!(convolutional::check_parameters(3, &[0o7, 0o5], Some(&vec![vec![1, 0]])).is_err());
+    assert!(
+        convolutional::check_parameters(3, &[0o7, 0o5], Some(&vec![vec![1, 0], vec![1, 0]]))
+            .is_err()
+    );
+
+    // K = 3, polynomials 7 and 5: input 1 0 1 1 with tail 0 0 gives 11 10 00 01 01 11
+    let code = ConvolutionalCode::new(3, vec![0o7, 0o5], None).unwrap();
+    let mut encoded = BitMap::new();
+    code.encode(&[1, 0, 1, 1], &mut encoded);
+    assert_eq!(
+        encoded.take_all_bits(),
+        [1, 1, 1, 0, 0, 0, 0, 1, 0, 1, 1, 1]
+    );
+    assert_eq!(code.encoded_length(4), 12);
+
+    let mut state = 11_u32;
+    let mut random = move || {
+        state = state.wrapping_mul(1103515245).wrapping_add(12345);
+        (state >> 8) as f64 / (1 << 24) as f64
+    };
+    let data: Vec<u8> = (0..1000).map(|_| (random() < 0.5) as u8).collect();
+
+    // scattered errors are corrected by hard decision decoding, punctured code included
+    for puncturing in [None, Some(vec![vec![1, 1, 0], vec![1, 0, 1]])] {
+        let code = ConvolutionalCode::new(7, vec![0o171, 0o133], puncturing.clone()).unwrap();
+        let mut encoded = BitMap::new();
+        code.encode(&data, &mut encoded);
+        let mut bits = encoded.take_all_bits();
+        assert_eq!(bits.len(), code.encoded_length(data.len()));
+        match puncturing {
+            None => assert_eq!(bits.len(), 2 * 1006),
+            Some(_) => assert_eq!(bits.len(), 1006 * 4 / 3 + 1),
+        }
+        assert_eq!(
+            code.decode_hard(&mut bits.iter(), data.len()).unwrap(),
+            data
+        );
+
+        for position in (0..bits.len()).step_by(60) {
+            bits[position] ^= 1;
+        }
+        assert_eq!(
+            code.decode_hard(&mut bits.iter(), data.len()).unwrap(),
+            data
+        );
+    }
+
+    // noisy channel: soft decisions lose less than hard ones
+    let code = ConvolutionalCode::new(5, vec![0o23, 0o35], None).unwrap();
+    let mut encoded = BitMap::new();
+    code.encode(&data, &mut encoded);
+    let sigma = 0.8;
+    let received: Vec<f64> = encoded
+        .take_all_bits()
+        .iter()
+        .map(|bit| {
+            // Box-Muller transform
+            let noise = (-2.0 * (1.0 - random()).ln()).sqrt()
+                * (2.0 * std::f64::consts::PI * random()).cos();
+            1.0 - 2.0 * *bit as f64 + sigma * noise
+        })
+        .collect();
+    let hard: Vec<u8> = received.iter().map(|value| (*value < 0.0) as u8).collect();
+
+    let errors = |decoded: Vec<u8>| decoded.iter().zip(&data).filter(|(a, b)| a != b).count();
+    let hard_errors = errors(code.decode_hard(&mut hard.iter(), data.len()).unwrap());
+    let soft_ratios: Vec<f64> = received
+        .iter()
+        .map(|value| 2.0 * value / (sigma * sigma))
+        .collect();
+    let soft_errors = errors(code.decode_soft(&soft_ratios, data.len()).unwrap());
+    assert!(soft_errors <= hard_errors);
+    assert!(code.decode_soft(&soft_ratios[1..], data.len()).is_err());
+
+    let error_correction = ErrorCorrection::Convolutional {
+        constraint_length: 7,
+        polynomials: vec![0o171, 0o133],
+        puncturing: Some(vec![vec![1, 1], vec![1, 0]]),
+    };
+    let dir = scratch_dir("convolutionalheader");
+    let path = dir.join("header.nk");
+    let header = ArchiveHeader::new(CodeType::Huffman, error_correction.clone(), 42);
+    header.write_to(&mut File::create(&path).unwrap()).unwrap();
+    let (read_header, offset) = ArchiveHeader::read_from(&File::open(&path).unwrap()).unwrap();
+    assert_eq!(header, read_header);
+    assert_eq!(offset, fs::metadata(&path).unwrap().len() as usize);
+    fs::remove_dir_all(&dir).unwrap();
+
+    let text = "Convolutional codes protect streams of bits. ".repeat(200);
+    let decoded = roundtrip_with("convolutional", text.as_bytes(), |settings| {
+        settings.error_correction = error_correction;
+    });
+    assert_eq!(decoded, text.as_bytes());
+}
+
+#[test]
+fn test_interleaving() {
+    assert!(interleaver::check_depth(1).is_err());
+    assert!(interleaver::check_depth(2).is_ok());
+
+    // two tables of 3 codewords by 4 bits
+    let bits: Vec<u8> = (0..24).collect();
+    let interleaved = interleaver::block_interleave(&bits, 3, 4);
+    assert_eq!(interleaved[..12], [0, 4, 8, 1, 5, 9, 2, 6, 10, 3, 7, 11]);
+    assert_eq!(interleaver::block_deinterleave(&interleaved, 3, 4), bits);
+
+    // every bit leaves deinterleaver after the same delay
+    let unit_delay = ConvolutionalInterleaver::unit_delay(4, 7);
+    let delay = ConvolutionalInterleaver::delay(4, unit_delay);
+    assert_eq!((unit_delay, delay), (2, 24));
+    let mut interleaver = ConvolutionalInterleaver::interleaver(4, unit_delay);
+    let mut deinterleaver = ConvolutionalInterleaver::deinterleaver(4, unit_delay);
+    let bits: Vec<u8> = (0..100).map(|i| (i * 7 % 3 == 0) as u8).collect();
+    let mut passed = deinterleaver.pass(&interleaver.pass(&bits));
+    passed.append(&mut deinterleaver.pass(&interleaver.pass(&vec![0; delay])));
+    assert!(passed[..delay].iter().all(|bit| *bit == 0));
+    assert_eq!(passed[delay..], bits[..]);
+
+    // interleaving is stored only for Hamming codes
+    assert!(channel::check_interleaving(
+        &Interleaving::Block { depth: 4 },
+        &ErrorCorrection::ReedSolomon { n: 255, k: 223 }
+    )
+    .is_err());
+    let dir = scratch_dir("interleavingheader");
+    let path = dir.join("header.nk");
+    let mut header = ArchiveHeader::new(CodeType::Huffman, hamming_code(15), 99);
+    header.interleaving = Interleaving::Convolutional { depth: 12 };
+    header.write_to(&mut File::create(&path).unwrap()).unwrap();
+    let (read_header, offset) = ArchiveHeader::read_from(&File::open(&path).unwrap()).unwrap();
+    assert_eq!(header, read_header);
+    assert_eq!(offset, fs::metadata(&path).unwrap().len() as usize);
+    fs::remove_dir_all(&dir).unwrap();
+
+    // a burst of 8 flipped bits is corrected by Hamming code only when its bits are interleaved
+    let text = b"interleaving spreads a burst over several codewords; ".repeat(40);
+    for (interleaving, survives) in [
+        (Interleaving::None, false),
+        (Interleaving::Block { depth: 8 }, true),
+        (Interleaving::Convolutional { depth: 8 }, true),
+    ] {
+        let dir = scratch_dir("interleaving");
+        let path = dir.join("sample.txt").to_str().unwrap().to_owned();
+        fs::write(&path, &text).unwrap();
+        let settings = EncodingSettings {
+            error_correction: hamming_code(7),
+            interleaving,
+            ..settings(&path, CodeType::Huffman)
+        };
+        encode_file(settings).unwrap();
+
+        let archive_path = path.clone() + ARCHIVE_EXTENSION;
+        let archive = File::options()
+            .read(true)
+            .write(true)
+            .open(&archive_path)
+            .unwrap();
+        let position = archive.metadata().unwrap().len() - 200;
+        let mut byte = [0];
+        archive.read_exact_at(&mut byte, position).unwrap();
+        archive.write_at(&[!byte[0]], position).unwrap();
+
+        let result = decode_file(Some((File::open(&archive_path).unwrap(), archive_path)));
+        assert_eq!(result.is_ok(), survives);
+        if survives {
+            assert_eq!(fs::read(dir.join("sample_1.txt")).unwrap(), text);
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    let decoded = roundtrip_with("interleavinglzw", &text, |settings| {
+        settings.coding = Coding::from(CodeType::Lzw);
+        settings.error_correction = ErrorCorrection::Hamming {
+            code_length: 16,
+            extended: true,
+        };
+        settings.interleaving = Interleaving::Block { depth: 5 };
+    });
+    assert_eq!(decoded, text);
+}
@@ -1,11 +1,214 @@
+use std::collections::HashMap;
 use std::fs::File;
+use strum_macros::FromRepr;
 
 // actual structs and types
 
-#[derive(Clone, Copy)]
+/// discriminants are stored in the archive header, so they must never be reordered
+#[derive(Clone, Copy, Debug, PartialEq, Eq, FromRepr)]
+#[repr(u8)]
 pub enum CodeType {
+    ShannonFano = 0,
+    Huffman = 1,
+    AdaptiveHuffman = 2,
+    Arithmetic = 3,
+    AdaptiveArithmetic = 4,
+    Ans = 5,
+    Lzss = 6,
+    Lzw = 7,
+    Bwt = 8,
+    ContextHuffman = 9,
+    Ppm = 10,
+    Shannon = 11,
+    ShannonFanoElias = 12,
+    DaryHuffman = 13,
+    HuTucker = 14,
+    Tunstall = 15,
+    NgramHuffman = 16,
+    WordHuffman = 17,
+}
+
+/// code type together with parameters of its coder, default value is used for a parameter
+/// that is not set
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Coding {
     ShannonFano,
-    Huffman,
+    /// longest allowed code, there is no limit when not set
+    Huffman {
+        max_code_length: Option<u8>,
+    },
+    AdaptiveHuffman,
+    Arithmetic,
+    AdaptiveArithmetic,
+    Ans,
+    /// log2 of window size
+    Lzss {
+        window_bits: Option<u8>,
+    },
+    /// widest code, dictionary is reset when codes do not fit it anymore
+    Lzw {
+        max_code_bits: Option<u8>,
+    },
+    Bwt,
+    ContextHuffman,
+    /// longest context of the model
+    Ppm {
+        order: Option<u8>,
+    },
+    Shannon,
+    ShannonFanoElias,
+    /// amount of code digits
+    DaryHuffman {
+        arity: Option<u8>,
+    },
+    HuTucker,
+    /// width of phrase index, dictionary holds up to 2^width phrases
+    Tunstall {
+        code_bits: Option<u8>,
+    },
+    /// bytes in a block
+    NgramHuffman {
+        length: Option<u8>,
+    },
+    WordHuffman,
+}
+
+impl Coding {
+    pub fn code_type(&self) -> CodeType {
+        match self {
+            Coding::ShannonFano => CodeType::ShannonFano,
+            Coding::Huffman { .. } => CodeType::Huffman,
+            Coding::AdaptiveHuffman => CodeType::AdaptiveHuffman,
+            Coding::Arithmetic => CodeType::Arithmetic,
+            Coding::AdaptiveArithmetic => CodeType::AdaptiveArithmetic,
+            Coding::Ans => CodeType::Ans,
+            Coding::Lzss { .. } => CodeType::Lzss,
+            Coding::Lzw { .. } => CodeType::Lzw,
+            Coding::Bwt => CodeType::Bwt,
+            Coding::ContextHuffman => CodeType::ContextHuffman,
+            Coding::Ppm { .. } => CodeType::Ppm,
+            Coding::Shannon => CodeType::Shannon,
+            Coding::ShannonFanoElias => CodeType::ShannonFanoElias,
+            Coding::DaryHuffman { .. } => CodeType::DaryHuffman,
+            Coding::HuTucker => CodeType::HuTucker,
+            Coding::Tunstall { .. } => CodeType::Tunstall,
+            Coding::NgramHuffman { .. } => CodeType::NgramHuffman,
+            Coding::WordHuffman => CodeType::WordHuffman,
+        }
+    }
+}
+
+/// coder with default parameters
+impl From<CodeType> for Coding {
+    fn from(code_type: CodeType) -> Self {
+        match code_type {
+            CodeType::ShannonFano => Coding::ShannonFano,
+            CodeType::Huffman => Coding::Huffman {
+                max_code_length: None,
+            },
+            CodeType::AdaptiveHuffman => Coding::AdaptiveHuffman,
+            CodeType::Arithmetic => Coding::Arithmetic,
+            CodeType::AdaptiveArithmetic => Coding::AdaptiveArithmetic,
+            CodeType::Ans => Coding::Ans,
+            CodeType::Lzss => Coding::Lzss { window_bits: None },
+            CodeType::Lzw => Coding::Lzw {
+                max_code_bits: None,
+            },
+            CodeType::Bwt => Coding::Bwt,
+            CodeType::ContextHuffman => Coding::ContextHuffman,
+            CodeType::Ppm => Coding::Ppm { order: None },
+            CodeType::Shannon => Coding::Shannon,
+            CodeType::ShannonFanoElias => Coding::ShannonFanoElias,
+            CodeType::DaryHuffman => Coding::DaryHuffman { arity: None },
+            CodeType::HuTucker => Coding::HuTucker,
+            CodeType::Tunstall => Coding::Tunstall { code_bits: None },
+            CodeType::NgramHuffman => Coding::NgramHuffman { length: None },
+            CodeType::WordHuffman => Coding::WordHuffman,
+        }
+    }
+}
+
+/// discriminants are stored in the archive header, so they must never be reordered
+#[derive(Clone, Copy, Debug, PartialEq, Eq, FromRepr)]
+#[repr(u8)]
+pub enum ErrorCorrectionScheme {
+    None = 0,
+    Hamming = 1,
+    ExtendedHamming = 2,
+    ReedSolomon = 3,
+    Convolutional = 4,
+}
+
+/// error correcting code that protects data bits of archive
+#[derive(Clone, Debug, PartialEq)]
+pub enum ErrorCorrection {
+    None,
+    /// blocks of `code_length` bits, extended ones end with parity of the whole block
+    Hamming {
+        code_length: u8,
+        extended: bool,
+    },
+    /// blocks of `n` bytes that carry `k` data bytes
+    ReedSolomon {
+        n: u8,
+        k: u8,
+    },
+    /// generator polynomials tap register of `constraint_length` bits; puncturing pattern has
+    /// a row of kept (1) and dropped (0) bits for every polynomial
+    Convolutional {
+        constraint_length: u8,
+        polynomials: Vec<u16>,
+        puncturing: Option<Vec<Vec<u8>>>,
+    },
+}
+
+impl ErrorCorrection {
+    pub fn scheme(&self) -> ErrorCorrectionScheme {
+        match self {
+            ErrorCorrection::None => ErrorCorrectionScheme::None,
+            ErrorCorrection::Hamming {
+                extended: false, ..
+            } => ErrorCorrectionScheme::Hamming,
+            ErrorCorrection::Hamming { extended: true, .. } => {
+                ErrorCorrectionScheme::ExtendedHamming
+            }
+            ErrorCorrection::ReedSolomon { .. } => ErrorCorrectionScheme::ReedSolomon,
+            ErrorCorrection::Convolutional { .. } => ErrorCorrectionScheme::Convolutional,
+        }
+    }
+}
+
+/// discriminants are stored in the archive header, so they must never be reordered
+#[derive(Clone, Copy, Debug, PartialEq, Eq, FromRepr)]
+#[repr(u8)]
+pub enum InterleavingScheme {
+    None = 0,
+    Block = 1,
+    Convolutional = 2,
+}
+
+/// reordering of codeword bits that spreads burst errors over several codewords
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Interleaving {
+    None,
+    /// tables of `depth` codewords read by columns
+    Block {
+        depth: u8,
+    },
+    /// `depth` branches with growing delays
+    Convolutional {
+        depth: u8,
+    },
+}
+
+impl Interleaving {
+    pub fn scheme(&self) -> InterleavingScheme {
+        match self {
+            Interleaving::None => InterleavingScheme::None,
+            Interleaving::Block { .. } => InterleavingScheme::Block,
+            Interleaving::Convolutional { .. } => InterleavingScheme::Convolutional,
+        }
+    }
 }
 
 /// byte -> its probability
@@ -17,6 +220,9 @@ pub type FileInfo = (File, String);
 // dictionary -> total count of symbols
 pub type FileStats = ([u64; 256], u64);
 
+// n-gram -> its count; total count of complete n-grams
+pub type NgramStats = (HashMap<u32, u64>, u64);
+
 #[derive(Copy, Clone, Debug)]
 pub struct Quantity {
     pub byte: u8,
@@ -25,8 +231,9 @@ pub struct Quantity {
 
 pub struct EncodingSettings {
     pub file_info: FileInfo,
-    pub code_type: CodeType,
-    pub hamming_code_length: Option<u8>,
+    pub coding: Coding,
+    pub error_correction: ErrorCorrection,
+    pub interleaving: Interleaving,
 }
 
 type NodePtr<T> = Option<Box<Node<T>>>;
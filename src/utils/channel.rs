@@ -0,0 +1,491 @@
+use super::file_reader::FileReader;
+use super::header::ArchiveHeader;
+use crate::algorithms::convolutional::{self, ConvolutionalCode};
+use crate::algorithms::hamming::{self, ParityReport};
+use crate::algorithms::interleaver::{self, ConvolutionalInterleaver};
+use crate::algorithms::reed_solomon::{self, ReedSolomon};
+use crate::bit_map::{BitMap, BitSource};
+use crate::types::{ErrorCorrection, Interleaving};
+use std::cell::RefCell;
+use std::fs::File;
+use std::io::{Error, ErrorKind};
+use std::rc::Rc;
+
+// Channel is the last stage of encoding and the first one of decoding: it protects data bits
+// with error correcting codes (if any are chosen) and moves them between memory and archive.
+// Reed-Solomon works on bytes: data bits are packed into bytes the same way `BitMap` does it,
+// so every byte of its block is a byte of archive and a damaged byte spoils a single symbol.
+// Bits of Hamming codewords may be interleaved after encoding and deinterleaved before decoding
+
+pub fn check_error_correction(error_correction: &ErrorCorrection) -> Result<(), Error> {
+    match error_correction {
+        ErrorCorrection::None => Ok(()),
+        ErrorCorrection::Hamming {
+            code_length,
+            extended,
+        } => hamming::check_code_length(*code_length, *extended),
+        ErrorCorrection::ReedSolomon { n, k } => reed_solomon::check_parameters(*n, *k),
+        ErrorCorrection::Convolutional {
+            constraint_length,
+            polynomials,
+            puncturing,
+        } => convolutional::check_parameters(*constraint_length, polynomials, puncturing.as_ref()),
+    }
+}
+
+/// interleaving spreads bits of Hamming codewords only, other codes handle bursts themselves
+pub fn check_interleaving(
+    interleaving: &Interleaving,
+    error_correction: &ErrorCorrection,
+) -> Result<(), Error> {
+    match (interleaving, error_correction) {
+        (Interleaving::None, _) => Ok(()),
+        (
+            Interleaving::Block { depth } | Interleaving::Convolutional { depth },
+            ErrorCorrection::Hamming { .. },
+        ) => interleaver::check_depth(*depth),
+        _ => Err(Error::new(
+            ErrorKind::InvalidInput,
+            "Interleaving is supported only with Hamming codes",
+        )),
+    }
+}
+
+/// error correcting code prepared for use
+enum Protection {
+    Hamming {
+        code_length: usize,
+        data_length: usize,
+        extended: bool,
+    },
+    ReedSolomon(Box<ReedSolomon>),
+    Convolutional(ConvolutionalCode),
+}
+
+impl Protection {
+    fn new(error_correction: &ErrorCorrection) -> Result<Option<Self>, Error> {
+        check_error_correction(error_correction)?;
+
+        Ok(match error_correction {
+            ErrorCorrection::None => None,
+            ErrorCorrection::Hamming {
+                code_length,
+                extended,
+            } => Some(Protection::Hamming {
+                code_length: *code_length as usize,
+                data_length: hamming::block_data_length(*code_length as usize, *extended),
+                extended: *extended,
+            }),
+            ErrorCorrection::ReedSolomon { n, k } => {
+                Some(Protection::ReedSolomon(Box::new(ReedSolomon::new(*n, *k)?)))
+            }
+            ErrorCorrection::Convolutional {
+                constraint_length,
+                polynomials,
+                puncturing,
+            } => Some(Protection::Convolutional(ConvolutionalCode::new(
+                *constraint_length,
+                polynomials.clone(),
+                puncturing.clone(),
+            )?)),
+        })
+    }
+
+    /// data bits in one block
+    fn data_bits(&self) -> usize {
+        match self {
+            Protection::Hamming { data_length, .. } => *data_length,
+            Protection::ReedSolomon(code) => 8 * code.data_length(),
+            Protection::Convolutional(_) => convolutional::FRAME_LENGTH,
+        }
+    }
+
+    /// bits of one encoded block
+    fn block_bits(&self) -> usize {
+        match self {
+            Protection::Hamming { code_length, .. } => *code_length,
+            Protection::ReedSolomon(code) => 8 * code.block_length(),
+            Protection::Convolutional(code) => code.encoded_length(convolutional::FRAME_LENGTH),
+        }
+    }
+
+    /// the last block is padded with zeros
+    fn encode(&self, bits: &[u8]) -> Vec<u8> {
+        match self {
+            Protection::Hamming {
+                data_length,
+                extended,
+                ..
+            } => hamming::add_parity_package(bits, *data_length, *extended),
+            Protection::ReedSolomon(code) => {
+                bytes_to_bits(&code.encode_package(&bits_to_bytes(bits)))
+            }
+            Protection::Convolutional(code) => {
+                let mut encoded = BitMap::new();
+                for frame in bits.chunks(convolutional::FRAME_LENGTH) {
+                    let mut frame = frame.to_vec();
+                    frame.resize(convolutional::FRAME_LENGTH, 0);
+                    code.encode(&frame, &mut encoded);
+                }
+                encoded.take_all_bits()
+            }
+        }
+    }
+
+    /// `bits` must consist of whole blocks
+    fn decode(&self, bits: &mut [u8]) -> (Vec<u8>, ParityReport) {
+        match self {
+            Protection::Hamming {
+                code_length,
+                extended,
+                ..
+            } => hamming::remove_parity_package(bits, *code_length, *extended),
+            Protection::ReedSolomon(code) => {
+                let (data, report) = code.decode_package(&mut bits_to_bytes(bits));
+                (bytes_to_bits(&data), report)
+            }
+            // Viterbi decoder can not detect errors it fails to correct, bits that differ from
+            // the encoded decoded data are reported as corrected ones
+            Protection::Convolutional(code) => {
+                let mut data = vec![];
+                let mut report = ParityReport::default();
+                let frame_bits = code.encoded_length(convolutional::FRAME_LENGTH);
+
+                for frame in bits.chunks(frame_bits) {
+                    let mut decoded = code
+                        .decode_hard(&mut frame.iter(), convolutional::FRAME_LENGTH)
+                        .expect("package consists of whole frames");
+
+                    let mut expected = BitMap::new();
+                    code.encode(&decoded, &mut expected);
+                    for (position, (bit, expected_bit)) in
+                        frame.iter().zip(expected.take_all_bits()).enumerate()
+                    {
+                        if *bit != expected_bit {
+                            report.corrected.push((report.blocks_checked, position));
+                        }
+                    }
+                    report.blocks_checked += 1;
+                    data.append(&mut decoded);
+                }
+
+                (data, report)
+            }
+        }
+    }
+}
+
+/// interleaver (or deinterleaver) prepared for use
+enum Interleaver {
+    Block {
+        depth: usize,
+        codeword_length: usize,
+        pending_bits: Vec<u8>, // received bits that do not fill a whole table yet
+    },
+    Convolutional {
+        interleaver: ConvolutionalInterleaver,
+        tail_bits: usize,   // zeros that flush interleaver at the end of data
+        filler_bits: usize, // bits deinterleaver outputs before the first data bit
+    },
+}
+
+impl Interleaver {
+    fn new(interleaving: Interleaving, codeword_length: usize, inverse: bool) -> Option<Self> {
+        match interleaving {
+            Interleaving::None => None,
+            Interleaving::Block { depth } => Some(Interleaver::Block {
+                depth: depth as usize,
+                codeword_length,
+                pending_bits: Vec::new(),
+            }),
+            Interleaving::Convolutional { depth } => {
+                let depth = depth as usize;
+                let unit_delay = ConvolutionalInterleaver::unit_delay(depth, codeword_length);
+                let delay = ConvolutionalInterleaver::delay(depth, unit_delay);
+
+                Some(match inverse {
+                    false => Interleaver::Convolutional {
+                        interleaver: ConvolutionalInterleaver::interleaver(depth, unit_delay),
+                        tail_bits: delay,
+                        filler_bits: 0,
+                    },
+                    true => Interleaver::Convolutional {
+                        interleaver: ConvolutionalInterleaver::deinterleaver(depth, unit_delay),
+                        tail_bits: 0,
+                        filler_bits: delay,
+                    },
+                })
+            }
+        }
+    }
+
+    /// codewords that are interleaved together
+    fn codewords(&self) -> usize {
+        match self {
+            Interleaver::Block { depth, .. } => *depth,
+            Interleaver::Convolutional { .. } => 1,
+        }
+    }
+
+    /// `bits` must consist of whole groups of `codewords()`
+    fn interleave(&mut self, bits: &[u8]) -> Vec<u8> {
+        match self {
+            Interleaver::Block {
+                depth,
+                codeword_length,
+                ..
+            } => interleaver::block_interleave(bits, *depth, *codeword_length),
+            Interleaver::Convolutional { interleaver, .. } => interleaver.pass(bits),
+        }
+    }
+
+    /// bits that must follow the last interleaved ones
+    fn tail(&mut self) -> Vec<u8> {
+        match self {
+            Interleaver::Block { .. } => vec![],
+            Interleaver::Convolutional {
+                interleaver,
+                tail_bits,
+                ..
+            } => interleaver.pass(&vec![0; *tail_bits]),
+        }
+    }
+
+    /// returns bits in the order of codewords as soon as they are known
+    fn deinterleave(&mut self, bits: &[u8]) -> Vec<u8> {
+        match self {
+            Interleaver::Block {
+                depth,
+                codeword_length,
+                pending_bits,
+            } => {
+                pending_bits.extend_from_slice(bits);
+                let table_bits = *depth * *codeword_length;
+                let complete_len = pending_bits.len() - pending_bits.len() % table_bits;
+                let tables: Vec<u8> = pending_bits.drain(..complete_len).collect();
+                interleaver::block_deinterleave(&tables, *depth, *codeword_length)
+            }
+            Interleaver::Convolutional {
+                interleaver,
+                filler_bits,
+                ..
+            } => {
+                let mut bits = interleaver.pass(bits);
+                let filler = bits.len().min(*filler_bits);
+                bits.drain(..filler);
+                *filler_bits -= filler;
+                bits
+            }
+        }
+    }
+}
+
+/// packs bits into bytes the same way `BitMap` does, the last byte is padded with zeros
+fn bits_to_bytes(bits: &[u8]) -> Vec<u8> {
+    bits.chunks(8)
+        .map(|byte| {
+            byte.iter()
+                .enumerate()
+                .fold(0, |packed, (i, bit)| packed | (bit << i))
+        })
+        .collect()
+}
+
+fn bytes_to_bits(bytes: &[u8]) -> Vec<u8> {
+    let mut bitmap = BitMap::new();
+    bitmap.add_bytes(bytes);
+    bitmap.get_all_bits()
+}
+
+/// collects encoded bits in `data()` and writes them to file on every `flush`
+pub struct ChannelWriter<'a> {
+    file: &'a mut File,
+    data: BitMap,
+    protection: Option<Protection>,
+    interleaver: Option<Interleaver>,
+    pending_bits: Vec<u8>, // data bits that did not fill a whole block yet
+    encoded: BitMap,
+}
+
+impl<'a> ChannelWriter<'a> {
+    pub fn new(file: &'a mut File, archive_header: &ArchiveHeader) -> Result<Self, Error> {
+        check_interleaving(
+            &archive_header.interleaving,
+            &archive_header.error_correction,
+        )?;
+        let protection = Protection::new(&archive_header.error_correction)?;
+        let interleaver = protection.as_ref().and_then(|protection| {
+            Interleaver::new(archive_header.interleaving, protection.block_bits(), false)
+        });
+
+        Ok(Self {
+            file,
+            data: BitMap::new(),
+            protection,
+            interleaver,
+            pending_bits: Vec::new(),
+            encoded: BitMap::new(),
+        })
+    }
+
+    /// bits added here are written to file by next `flush`
+    pub fn data(&mut self) -> &mut BitMap {
+        &mut self.data
+    }
+
+    /// writes all complete bytes (or blocks of error correcting code); on end of data pads and
+    /// writes the rest
+    pub fn flush(&mut self, end_of_data: bool) -> Result<(), Error> {
+        let result = match &self.protection {
+            Some(protection) => {
+                self.pending_bits.append(&mut self.data.take_all_bits());
+
+                // only the very last block is padded, so padding never appears in the middle of data;
+                // interleaved codewords go in whole groups, so the last group is padded with zeros
+                let codewords = self.interleaver.as_ref().map_or(1, |i| i.codewords());
+                let data_bits = protection.data_bits() * codewords;
+                if end_of_data && codewords > 1 {
+                    let padded_len = self.pending_bits.len().div_ceil(data_bits) * data_bits;
+                    self.pending_bits.resize(padded_len, 0);
+                }
+                let complete_len = if end_of_data {
+                    self.pending_bits.len()
+                } else {
+                    self.pending_bits.len() - self.pending_bits.len() % data_bits
+                };
+
+                let mut encoded = protection.encode(&self.pending_bits[..complete_len]);
+                self.pending_bits.drain(..complete_len);
+                if let Some(interleaver) = &mut self.interleaver {
+                    encoded = interleaver.interleave(&encoded);
+                    if end_of_data {
+                        encoded.append(&mut interleaver.tail());
+                    }
+                }
+                self.encoded.add_bit_sequence(&encoded);
+
+                if end_of_data {
+                    self.encoded.flush_to_file(self.file)
+                } else {
+                    self.encoded.flush_filled_to_file(self.file)
+                }
+            }
+            None => {
+                if end_of_data {
+                    self.data.flush_to_file(self.file)
+                } else {
+                    self.data.flush_filled_to_file(self.file)
+                }
+            }
+        };
+
+        result.map_err(|_| Error::new(ErrorKind::BrokenPipe, "Error while writing to file"))
+    }
+}
+
+/// reads data bits of archive, correcting errors with error correcting code (if it was used);
+/// errors found in its blocks are added to the shared report
+pub struct ChannelReader {
+    file_reader: FileReader,
+    protection: Option<Protection>,
+    interleaver: Option<Interleaver>,
+    report: Rc<RefCell<ParityReport>>,
+    chunk: Vec<u8>,
+    chunk_position: usize,
+    connecting_bits: Vec<u8>, // bits that do not form a whole block yet
+    bits: Vec<u8>,
+    bit_position: usize,
+}
+
+impl ChannelReader {
+    const REFILL_SIZE: usize = 1024; // bytes converted to bits at once
+
+    /// `file_reader` must be positioned at the first data byte
+    pub fn new(
+        file_reader: FileReader,
+        archive_header: &ArchiveHeader,
+        report: Rc<RefCell<ParityReport>>,
+    ) -> Result<Self, Error> {
+        check_interleaving(
+            &archive_header.interleaving,
+            &archive_header.error_correction,
+        )?;
+        let protection = Protection::new(&archive_header.error_correction)?;
+        let interleaver = protection.as_ref().and_then(|protection| {
+            Interleaver::new(archive_header.interleaving, protection.block_bits(), true)
+        });
+
+        Ok(Self {
+            file_reader,
+            protection,
+            interleaver,
+            report,
+            chunk: Vec::new(),
+            chunk_position: 0,
+            connecting_bits: Vec::new(),
+            bits: Vec::new(),
+            bit_position: 0,
+        })
+    }
+
+    /// returns false when there is no data left
+    fn refill(&mut self) -> Result<bool, Error> {
+        self.bits.clear();
+        self.bit_position = 0;
+
+        while self.bits.is_empty() {
+            if self.chunk_position == self.chunk.len() {
+                self.chunk = self.file_reader.read_next_chunk()?.to_vec();
+                self.chunk_position = 0;
+
+                if self.chunk.is_empty() {
+                    return Ok(false);
+                }
+            }
+
+            let end = self
+                .chunk
+                .len()
+                .min(self.chunk_position + Self::REFILL_SIZE);
+            let mut bitmap = BitMap::new();
+            bitmap.add_bytes(&self.chunk[self.chunk_position..end]);
+            self.chunk_position = end;
+
+            let mut bits = bitmap.get_all_bits();
+
+            if let Some(protection) = &self.protection {
+                if let Some(interleaver) = &mut self.interleaver {
+                    bits = interleaver.deinterleave(&bits);
+                }
+                self.connecting_bits.append(&mut bits);
+
+                let msg_len = protection.block_bits();
+                let connection_pos =
+                    self.connecting_bits.len() - self.connecting_bits.len() % msg_len;
+                let mut messages: Vec<u8> = self.connecting_bits.drain(..connection_pos).collect();
+
+                let (data, report) = protection.decode(&mut messages);
+                self.report.borrow_mut().append(report);
+                bits = data;
+            }
+
+            self.bits = bits;
+        }
+
+        Ok(true)
+    }
+}
+
+impl BitSource for ChannelReader {
+    fn read_bit(&mut self) -> Result<u8, Error> {
+        if self.bit_position == self.bits.len() && !self.refill()? {
+            return Err(Error::new(
+                ErrorKind::UnexpectedEof,
+                "Archive data ended unexpectedly",
+            ));
+        }
+
+        self.bit_position += 1;
+        Ok(self.bits[self.bit_position - 1])
+    }
+}
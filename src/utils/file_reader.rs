@@ -6,7 +6,8 @@ use super::constants::DEFAULT_BUFFER_SIZE;
 
 pub struct FileReader {
     current_position: usize,
-    buffer: [u8; DEFAULT_BUFFER_SIZE],
+    end_position: Option<usize>, // reading stops here instead of the end of file when set
+    buffer: Box<[u8]>,           // kept on heap as it is too large for stacks of spawned threads
     file: File,
 }
 
@@ -15,7 +16,8 @@ impl FileReader {
     pub fn new(file: File) -> Self {
         Self {
             current_position: 0,
-            buffer: [0; DEFAULT_BUFFER_SIZE],
+            end_position: None,
+            buffer: vec![0; DEFAULT_BUFFER_SIZE].into_boxed_slice(),
             file: file,
         }
     }
@@ -32,21 +34,9 @@ impl FileReader {
     where
         F: FnMut(&[u8], bool) -> Result<(), Error>,
     {
-        let file_size = self.file.metadata().unwrap().len();
-
         loop {
-            let bytes_read = match self
-                .file
-                .read_at(&mut self.buffer, self.current_position as u64)
-            {
-                Ok(size) => size,
-                Err(err) => {
-                    return Err(Error::new(ErrorKind::Other, err));
-                }
-            };
-
-            self.current_position += bytes_read;
-            let finished_file = self.current_position as u64 >= file_size;
+            let bytes_read = self.fill_buffer()?;
+            let finished_file = self.current_position as u64 >= self.read_limit();
 
             action_on_chunk(&self.buffer[..bytes_read], finished_file)?;
 
@@ -56,10 +46,53 @@ impl FileReader {
         }
     }
 
+    /// reads next chunk of file, empty chunk means that there is nothing left
+    pub fn read_next_chunk(&mut self) -> Result<&[u8], Error> {
+        let bytes_read = self.fill_buffer()?;
+        Ok(&self.buffer[..bytes_read])
+    }
+
+    fn fill_buffer(&mut self) -> Result<usize, Error> {
+        let buffer_len = (self.read_limit() as usize)
+            .saturating_sub(self.current_position)
+            .min(self.buffer.len());
+
+        let bytes_read = match self
+            .file
+            .read_at(&mut self.buffer[..buffer_len], self.current_position as u64)
+        {
+            Ok(size) => size,
+            Err(err) => {
+                return Err(Error::new(ErrorKind::Other, err));
+            }
+        };
+
+        self.current_position += bytes_read;
+        Ok(bytes_read)
+    }
+
+    pub fn file_size(&self) -> Result<u64, Error> {
+        Ok(self.file.metadata()?.len())
+    }
+
+    /// position where reading stops
+    fn read_limit(&self) -> u64 {
+        let file_size = self.file.metadata().unwrap().len();
+
+        match self.end_position {
+            Some(end) => file_size.min(end as u64),
+            None => file_size,
+        }
+    }
+
     pub fn set_offset(&mut self, offset: usize) {
         self.current_position = offset;
     }
 
+    pub fn set_end(&mut self, end: usize) {
+        self.end_position = Some(end);
+    }
+
     pub fn rewind(&mut self) {
         self.current_position = 0;
     }
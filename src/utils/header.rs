@@ -0,0 +1,243 @@
+use super::channel;
+use super::constants::{ARCHIVE_MAGIC, FORMAT_VERSION};
+use crate::types::{
+    CodeType, ErrorCorrection, ErrorCorrectionScheme, Interleaving, InterleavingScheme,
+};
+use std::fs::File;
+use std::io::{Error, ErrorKind, Write};
+use std::os::unix::fs::FileExt;
+
+// Structure of archive header:
+// "Magic" (4 bytes) -> "Version" (1 byte) -> "Flags" (2 bytes, little endian)
+// -> "Original length" (8 bytes, little endian, count of bytes in original file) -> "Scheme parameters"
+// -> "Interleaver depth" (1 byte, present when the data is interleaved)
+// "Flags": bits 0-5 - code type, bits 6-9 - error correction scheme, bit 10 - checksum present,
+//          bits 11-12 - interleaving scheme, bits 13-15 - reserved and must be 0
+// "Scheme parameters": Hamming and extended Hamming - 1 byte with code length (the extended one
+//                      includes the overall parity bit); Reed-Solomon - 2 bytes: block length n and
+//                      data length k (in bytes); Convolutional - constraint length (1 byte), amount
+//                      of polynomials (1 byte), polynomials (2 bytes each, little endian), period of
+//                      puncturing pattern (1 byte, 0 - no puncturing) and its rows (a byte per bit);
+//                      None - nothing
+
+const CODE_TYPE_MASK: u16 = 0b11_1111;
+const ERROR_CORRECTION_SHIFT: u16 = 6;
+const ERROR_CORRECTION_MASK: u16 = 0b1111;
+const CHECKSUM_FLAG: u16 = 1 << 10;
+const INTERLEAVING_SHIFT: u16 = 11;
+const INTERLEAVING_MASK: u16 = 0b11;
+const RESERVED_FLAGS: u16 = !(CODE_TYPE_MASK
+    | (ERROR_CORRECTION_MASK << ERROR_CORRECTION_SHIFT)
+    | CHECKSUM_FLAG
+    | (INTERLEAVING_MASK << INTERLEAVING_SHIFT));
+
+const FIXED_PART_LENGTH: usize = ARCHIVE_MAGIC.len() + 1 + 2 + 8;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ArchiveHeader {
+    pub code_type: CodeType,
+    pub error_correction: ErrorCorrection,
+    pub has_checksum: bool,
+    pub interleaving: Interleaving,
+    pub original_length: u64,
+}
+
+impl ArchiveHeader {
+    pub fn new(
+        code_type: CodeType,
+        error_correction: ErrorCorrection,
+        original_length: u64,
+    ) -> Self {
+        Self {
+            code_type,
+            error_correction,
+            has_checksum: false,
+            interleaving: Interleaving::None,
+            original_length,
+        }
+    }
+
+    pub fn flags(&self) -> u16 {
+        let mut flags = self.code_type as u16 & CODE_TYPE_MASK;
+        flags |= (self.error_correction.scheme() as u16 & ERROR_CORRECTION_MASK)
+            << ERROR_CORRECTION_SHIFT;
+
+        if self.has_checksum {
+            flags |= CHECKSUM_FLAG;
+        }
+        flags |= (self.interleaving.scheme() as u16 & INTERLEAVING_MASK) << INTERLEAVING_SHIFT;
+
+        flags
+    }
+
+    pub fn write_to(&self, file: &mut File) -> Result<(), Error> {
+        file.write_all(&ARCHIVE_MAGIC)?;
+        file.write_all(&[FORMAT_VERSION])?;
+        file.write_all(&self.flags().to_le_bytes())?;
+        file.write_all(&self.original_length.to_le_bytes())?;
+
+        match &self.error_correction {
+            ErrorCorrection::None => {}
+            ErrorCorrection::Hamming { code_length, .. } => file.write_all(&[*code_length])?,
+            ErrorCorrection::ReedSolomon { n, k } => file.write_all(&[*n, *k])?,
+            ErrorCorrection::Convolutional {
+                constraint_length,
+                polynomials,
+                puncturing,
+            } => {
+                let mut parameters = vec![*constraint_length, polynomials.len() as u8];
+                for polynomial in polynomials {
+                    parameters.extend_from_slice(&polynomial.to_le_bytes());
+                }
+                match puncturing {
+                    Some(pattern) => {
+                        parameters.push(pattern[0].len() as u8);
+                        parameters.extend(pattern.iter().flatten());
+                    }
+                    None => parameters.push(0),
+                }
+                file.write_all(&parameters)?
+            }
+        }
+
+        match self.interleaving {
+            Interleaving::None => {}
+            Interleaving::Block { depth } | Interleaving::Convolutional { depth } => {
+                file.write_all(&[depth])?
+            }
+        }
+
+        Ok(())
+    }
+
+    /// returns parsed header and offset of the first byte after it
+    pub fn read_from(file: &File) -> Result<(Self, usize), Error> {
+        let mut buf = [0_u8; FIXED_PART_LENGTH];
+        if file.read_exact_at(&mut buf, 0).is_err() || buf[..ARCHIVE_MAGIC.len()] != ARCHIVE_MAGIC {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                "File is not a .nk archive: magic bytes are missing",
+            ));
+        }
+        let mut offset = ARCHIVE_MAGIC.len();
+
+        let version = buf[offset];
+        offset += 1;
+        if version != FORMAT_VERSION {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!(
+                    "Unsupported archive format version {} (this build reads version {})",
+                    version, FORMAT_VERSION
+                ),
+            ));
+        }
+
+        let flags = u16::from_le_bytes([buf[offset], buf[offset + 1]]);
+        offset += 2;
+        if flags & RESERVED_FLAGS != 0 {
+            return Err(invalid_header(format!(
+                "unknown feature flags {:#06x}",
+                flags
+            )));
+        }
+
+        let code_type = CodeType::from_repr((flags & CODE_TYPE_MASK) as u8).ok_or_else(|| {
+            invalid_header(format!("unknown code type {}", flags & CODE_TYPE_MASK))
+        })?;
+
+        let original_length = u64::from_le_bytes(buf[offset..offset + 8].try_into().unwrap());
+        offset += 8;
+
+        let scheme_id = (flags >> ERROR_CORRECTION_SHIFT) & ERROR_CORRECTION_MASK;
+        let mut read_parameters = |count: usize| -> Result<Vec<u8>, Error> {
+            let mut parameters = vec![0_u8; count];
+            file.read_exact_at(&mut parameters, offset as u64)?;
+            offset += count;
+            Ok(parameters)
+        };
+        let error_correction = match ErrorCorrectionScheme::from_repr(scheme_id as u8) {
+            Some(ErrorCorrectionScheme::None) => ErrorCorrection::None,
+            Some(
+                scheme @ (ErrorCorrectionScheme::Hamming | ErrorCorrectionScheme::ExtendedHamming),
+            ) => ErrorCorrection::Hamming {
+                code_length: read_parameters(1)?[0],
+                extended: scheme == ErrorCorrectionScheme::ExtendedHamming,
+            },
+            Some(ErrorCorrectionScheme::ReedSolomon) => {
+                let parameters = read_parameters(2)?;
+                ErrorCorrection::ReedSolomon {
+                    n: parameters[0],
+                    k: parameters[1],
+                }
+            }
+            Some(ErrorCorrectionScheme::Convolutional) => {
+                let sizes = read_parameters(2)?;
+                let polynomials: Vec<u16> = read_parameters(2 * sizes[1] as usize)?
+                    .chunks(2)
+                    .map(|bytes| u16::from_le_bytes([bytes[0], bytes[1]]))
+                    .collect();
+                let period = read_parameters(1)?[0] as usize;
+                let puncturing = match period {
+                    0 => None,
+                    _ => Some(
+                        read_parameters(period * polynomials.len())?
+                            .chunks(period)
+                            .map(|row| row.to_vec())
+                            .collect(),
+                    ),
+                };
+
+                ErrorCorrection::Convolutional {
+                    constraint_length: sizes[0],
+                    polynomials,
+                    puncturing,
+                }
+            }
+            None => {
+                return Err(invalid_header(format!(
+                    "unknown error correction scheme {}",
+                    scheme_id
+                )))
+            }
+        };
+        channel::check_error_correction(&error_correction)
+            .map_err(|err| invalid_header(err.to_string()))?;
+
+        let interleaving_id = (flags >> INTERLEAVING_SHIFT) & INTERLEAVING_MASK;
+        let interleaving = match InterleavingScheme::from_repr(interleaving_id as u8) {
+            Some(InterleavingScheme::None) => Interleaving::None,
+            Some(InterleavingScheme::Block) => Interleaving::Block {
+                depth: read_parameters(1)?[0],
+            },
+            Some(InterleavingScheme::Convolutional) => Interleaving::Convolutional {
+                depth: read_parameters(1)?[0],
+            },
+            None => {
+                return Err(invalid_header(format!(
+                    "unknown interleaving scheme {}",
+                    interleaving_id
+                )))
+            }
+        };
+        channel::check_interleaving(&interleaving, &error_correction)
+            .map_err(|err| invalid_header(err.to_string()))?;
+
+        let header = Self {
+            code_type,
+            error_correction,
+            has_checksum: flags & CHECKSUM_FLAG != 0,
+            interleaving,
+            original_length,
+        };
+
+        Ok((header, offset))
+    }
+}
+
+fn invalid_header(reason: String) -> Error {
+    Error::new(
+        ErrorKind::InvalidData,
+        format!("Corrupted archive header: {}", reason),
+    )
+}
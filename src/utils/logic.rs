@@ -1,19 +1,39 @@
-use super::constants::HAMMING_CODE_LENGTH_KEY;
+use super::channel::{check_error_correction, check_interleaving, ChannelReader, ChannelWriter};
 use super::file_reader::FileReader;
-use super::formulae::parse_chunk_for_unique_bytes;
+use super::formulae::{
+    calculate_entropy, calculate_information_amount, parse_chunk_for_unique_bytes,
+};
+use super::header::ArchiveHeader;
 use super::terminal::get_input_from_user;
-use super::{clear, get_file, get_stats_and_print, parse_file};
-use crate::algorithms::{hamming, huffman, shannon_fano};
-use crate::bit_map::BitMap;
-use crate::types::{CodeType, EncodingSettings, FileInfo};
-use crate::utils::constants::{ARCHIVE_EXTENSION, DICTIONARY_END};
-use std::cmp::min;
+use super::{clear, get_file, get_stats_and_print, parse_file, parse_file_ngrams};
+use crate::algorithms::adaptive_huffman::AdaptiveHuffman;
+use crate::algorithms::ans::{AnsDecoder, AnsEncoder, AnsTable};
+use crate::algorithms::arithmetic::{self, ArithmeticDecoder, ArithmeticEncoder, FrequencyModel};
+use crate::algorithms::bwt::{self, Blocks, Symbol};
+use crate::algorithms::canonical::{self, CanonicalDecoder};
+use crate::algorithms::context::{self, ContextCodes, ContextDecoder, ContextStats};
+use crate::algorithms::crc32::Crc32;
+use crate::algorithms::hamming::ParityReport;
+use crate::algorithms::hu_tucker::{self, AlphabeticDecoder};
+use crate::algorithms::huffman::{DigitReader, DigitWriter};
+use crate::algorithms::lzss::{self, LzssDecoder, LzssEncoder, Token, MIN_MATCH};
+use crate::algorithms::lzw::{self, LzwDecoder, LzwEncoder};
+use crate::algorithms::ngram::{self, Ngrams};
+use crate::algorithms::ppm::{self, PpmModel};
+use crate::algorithms::tunstall::{self, TunstallCode, TunstallEncoder};
+use crate::algorithms::words::{Tokenizer, Vocabulary, WordCode, WordDecoder};
+use crate::algorithms::{huffman, shannon, shannon_fano, shannon_fano_elias, universal};
+use crate::bit_map::{BitMap, BitSource};
+use crate::types::{CodeType, Coding, EncodingSettings, ErrorCorrection, FileInfo};
+use crate::utils::constants::{ARCHIVE_EXTENSION, CHECKSUM_LENGTH};
+use std::cell::RefCell;
 use std::collections::HashMap;
+use std::fmt::Display;
 use std::fs::File;
-use std::io::{Error, ErrorKind, Write};
+use std::io::{Error, ErrorKind, Seek, Write};
 use std::ops::Add;
 use std::os::unix::fs::FileExt;
-use trie_rs::Trie;
+use std::rc::Rc;
 
 pub fn calculate_user_input_stats() {
     println!("Please input the message followed by hitting 'ctrl+d'");
@@ -27,6 +47,9 @@ pub fn calculate_user_input_stats() {
 
     clear();
     get_stats_and_print(&dictionary, size);
+
+    let mut model = PpmModel::new(ppm::DEFAULT_ORDER).unwrap();
+    print_ppm_estimate(model.code_length(&buf), size);
 }
 
 pub fn calculate_file_stats() {
@@ -39,15 +62,101 @@ pub fn calculate_file_stats() {
     let (dictionary, file_size) = parse_file(&mut file_reader);
 
     get_stats_and_print(&dictionary, file_size);
+
+    let mut model = PpmModel::new(ppm::DEFAULT_ORDER).unwrap();
+    let mut code_length = 0.0;
+    file_reader.rewind();
+    let estimated = file_reader.read_file_in_chunks(|buf, _| {
+        code_length += model.code_length(buf);
+        Ok(())
+    });
+    if estimated.is_ok() {
+        print_ppm_estimate(code_length, file_size);
+    }
+}
+
+fn print_ppm_estimate(code_length: f64, size: u64) {
+    if size != 0 {
+        println!(
+            "PPM of order {} reaches {:.3} bits per symbol",
+            ppm::DEFAULT_ORDER,
+            code_length / size as f64
+        );
+    }
 }
 
 // Structure of encoded file:
-// "Header" -> "Data"
-// "Header": N entries of "Mapping"
-// "Mapping": 1st byte - original symbol, 2nd byte - LENGTH of BIT CODE stored in next 'ceil(LENGTH / 8)' bytes of "Mapping"
-// *Note. Last bits that are not filled in last byte of BIT CODE are set to 0
+// "Archive header" -> "Dictionary" -> "Data" -> "Trailer"
+// "Archive header": see utils/header.rs
+// "Dictionary": "Max length" -> "Counts" -> "Symbols"
+// "Max length" and "Counts" are Exp-Golomb codes of order 0 (see algorithms/universal.rs), padded
+// with zero bits to a whole byte
+// "Counts": for every code length from 1 to "Max length" - amount of codes of that length
+// "Symbols": original symbols sorted by code length and then by value (1 byte each)
+// *Note. Codes are canonical, so decoder rebuilds them from lengths alone; Hu-Tucker codes are
+// assigned in order of symbols instead, which keeps them alphabetic
+// "Frequencies" (instead of "Dictionary" for arithmetic and rANS coding): "Count" (2 bytes, little endian)
+// followed by "Count" entries of symbol (1 byte) and its scaled frequency (2 bytes, little endian)
+// *Note. rANS frequencies always sum to 4096; its data is a sequence of blocks, each starting with coder state (4 bytes)
+// *Note. Adaptive coders store neither "Dictionary" nor "Frequencies"
+// "LZSS" (instead of "Dictionary" for LZSS coding): "Window bits" (1 byte) followed by three
+// "Dictionary" sections: of literals, of match lengths (minus 3) and of distance slots
+// *Note. Every LZSS token in "Data" is a flag bit (0 - literal, 1 - match) followed by code of
+// literal or by codes of length and distance slot and raw extra bits of distance
+// "Code bits" (instead of "Dictionary" for LZW coding): maximal width of LZW codes (1 byte)
+// "BWT" (instead of "Dictionary" for block-sorting): two "Dictionary" sections, of move-to-front
+// values and of zero run lengths (minus 1)
+// "Contexts" (instead of "Dictionary" for order-1 context coding): bit set of contexts that have
+// own tables (32 bytes, bit i of byte j is context 8 * j + i), "Dictionary" of the shared table
+// for the rest of contexts, then "Dictionary" of every context with own table in their order
+// "Order" (instead of "Dictionary" for PPM): the longest context of the model (1 byte)
+// "N-grams" (instead of "Dictionary" for n-gram Huffman coding): length of n-gram (1 byte) followed by
+// "Dictionary" section whose every symbol is an n-gram ("Length" bytes)
+// *Note. N-gram "Data" ends with bytes of the tail that do not fill a whole n-gram, 8 bits each
+// "Words" (instead of "Dictionary" for word-based Huffman coding): "Max length" and "Counts" as in
+// "Dictionary" section followed by tokens in canonical order, each one is its length (1 byte) and bytes
+// "Tunstall" (instead of "Dictionary" for Tunstall coding): width of phrase index (1 byte) followed
+// by "Frequencies" section; decoder rebuilds dictionary of phrases from them
+// "Arity" (before "Dictionary" for D-ary Huffman): amount of code digits (1 byte); the "Dictionary"
+// holds code lengths in digits and codes are canonical over those digits
+// *Note. D-ary "Data" is packed by groups of digits, every group is a number in base "Arity" written
+// with the least bits that fit it; groups take at most 16 bits and are chosen to waste the least bits
+// per digit (e.g. 5 ternary digits take 8 bits); the last group is padded with zeros
+// *Note. Every block in BWT "Data" starts with its primary index (32 bits) followed by codes of
+// values; value 0 is followed by code of run length
+// "Trailer": CRC-32 of original file (4 bytes, little endian), present when header has checksum flag
+
+/// checks parameters of the coder that are set, so encoding does not fail after archive is created
+pub fn check_coding(coding: &Coding) -> Result<(), Error> {
+    match *coding {
+        Coding::Huffman {
+            max_code_length: Some(0),
+        } => Err(Error::new(
+            ErrorKind::InvalidInput,
+            "Code length must be in range of 1 - 255",
+        )),
+        Coding::Lzss {
+            window_bits: Some(window_bits),
+        } => lzss::window_size(window_bits).map(|_| ()),
+        Coding::Lzw {
+            max_code_bits: Some(max_bits),
+        } => lzw::check_max_bits(max_bits),
+        Coding::Ppm { order: Some(order) } => ppm::check_order(order),
+        Coding::DaryHuffman { arity: Some(arity) } => huffman::check_arity(arity),
+        Coding::Tunstall {
+            code_bits: Some(code_bits),
+        } => tunstall::check_code_bits(code_bits),
+        Coding::NgramHuffman {
+            length: Some(length),
+        } => ngram::check_length(length),
+        _ => Ok(()),
+    }
+}
 
 pub fn encode_file(settings: EncodingSettings) -> Result<(), Error> {
+    check_coding(&settings.coding)?;
+    check_error_correction(&settings.error_correction)?;
+    check_interleaving(&settings.interleaving, &settings.error_correction)?;
     let (original_file, input_path) = settings.file_info;
 
     let out_path = input_path.to_owned().add(ARCHIVE_EXTENSION);
@@ -55,166 +164,698 @@ pub fn encode_file(settings: EncodingSettings) -> Result<(), Error> {
         (); // probably could not delete file as it does not exist
     }
     let mut file_reader = FileReader::new(original_file);
-
-    let dictionary = match settings.code_type {
-        CodeType::ShannonFano => shannon_fano::encode(parse_file(&mut file_reader)),
-        CodeType::Huffman => huffman::encode(parse_file(&mut file_reader)),
+    let original_length = file_reader.file_size()?;
+
+    // code length limit may not fit the file, so prefix code is built before archive is created
+    let dictionary = match settings.coding {
+        Coding::ShannonFano
+        | Coding::Huffman { .. }
+        | Coding::Shannon
+        | Coding::ShannonFanoElias
+        | Coding::HuTucker => build_dictionary(&mut file_reader, settings.coding)?,
+        _ => HashMap::new(),
     };
 
     let mut output_file = File::create(&out_path).unwrap();
 
-    if let Err(err) =
-        create_dictionary_header(&mut output_file, &dictionary, settings.hamming_code_length)
-    {
-        return Err(err);
-    }
+    let mut archive_header = ArchiveHeader::new(
+        settings.coding.code_type(),
+        settings.error_correction,
+        original_length,
+    );
+    archive_header.has_checksum = true;
+    archive_header.interleaving = settings.interleaving;
+    archive_header.write_to(&mut output_file)?;
+
+    let checksum = match settings.coding {
+        Coding::ShannonFano
+        | Coding::Huffman { .. }
+        | Coding::Shannon
+        | Coding::ShannonFanoElias
+        | Coding::HuTucker => {
+            create_dictionary_header(&mut output_file, &dictionary)?;
+
+            write_compressed_file(
+                &mut file_reader,
+                &mut output_file,
+                &archive_header,
+                |buf, _, bits| {
+                    for byte in buf {
+                        bits.add_bit_sequence(&dictionary[byte]);
+                    }
+                },
+            )?
+        }
+        Coding::DaryHuffman { arity } => {
+            let arity = arity.unwrap_or(huffman::DEFAULT_ARITY);
+            let mut writer = DigitWriter::new(arity)?;
+            let dictionary = build_d_ary_dictionary(&mut file_reader, arity);
+            output_file.write_all(&[arity])?;
+            create_dictionary_header(&mut output_file, &dictionary)?;
+
+            write_compressed_file(
+                &mut file_reader,
+                &mut output_file,
+                &archive_header,
+                |buf, end_of_file, bits| {
+                    for byte in buf {
+                        writer.write(&dictionary[byte], bits);
+                    }
+                    if end_of_file {
+                        writer.finish(bits);
+                    }
+                },
+            )?
+        }
+        Coding::Tunstall { code_bits } => {
+            let code_bits = code_bits.unwrap_or(tunstall::DEFAULT_CODE_BITS);
+            let frequencies = tunstall::scale_frequencies(&parse_file(&mut file_reader));
+            let code = TunstallCode::new(&frequencies, code_bits)?;
+            println!(
+                "Dictionary has {} phrases of {} bits",
+                code.phrase_count(),
+                code.code_bits()
+            );
+
+            output_file.write_all(&[code_bits])?;
+            create_frequency_header(&mut output_file, &frequencies)?;
+
+            let mut coder = TunstallEncoder::new(code);
+            write_compressed_file(
+                &mut file_reader,
+                &mut output_file,
+                &archive_header,
+                |buf, end_of_file, bits| {
+                    coder.encode(buf, bits);
+                    if end_of_file {
+                        coder.finish(bits);
+                    }
+                },
+            )?
+        }
+        Coding::NgramHuffman { length } => {
+            let length = length.unwrap_or(ngram::DEFAULT_LENGTH);
+            let dictionary = build_ngram_dictionary(&mut file_reader, length);
+            create_ngram_header(&mut output_file, length, &dictionary)?;
+
+            let mut ngrams = Ngrams::new(length);
+            write_compressed_file(
+                &mut file_reader,
+                &mut output_file,
+                &archive_header,
+                |buf, end_of_file, bits| {
+                    ngrams.push(buf, |ngram| bits.add_bit_sequence(&dictionary[&ngram]));
+                    if end_of_file {
+                        for byte in ngrams.tail() {
+                            bits.add_value(*byte as u64, 8);
+                        }
+                    }
+                },
+            )?
+        }
+        Coding::WordHuffman => {
+            let mut quantities: HashMap<Vec<u8>, u64> = HashMap::new();
+            let mut tokenizer = Tokenizer::new();
+            file_reader.rewind();
+            file_reader.read_file_in_chunks(|buf, end_of_file| {
+                tokenizer.push(buf, end_of_file, |token| {
+                    *quantities.entry(token.to_vec()).or_insert(0) += 1;
+                });
+                Ok(())
+            })?;
+
+            let code = WordCode::build(&quantities);
+            let vocabulary_start = output_file.stream_position()?;
+            create_words_header(&mut output_file, &code)?;
+            println!(
+                "Vocabulary has {} tokens and takes {} bytes",
+                code.tokens.len(),
+                output_file.stream_position()? - vocabulary_start
+            );
+
+            write_compressed_file(
+                &mut file_reader,
+                &mut output_file,
+                &archive_header,
+                |buf, end_of_file, bits| {
+                    tokenizer.push(buf, end_of_file, |token| {
+                        bits.add_bit_sequence(code.code(token));
+                    });
+                },
+            )?
+        }
+        Coding::AdaptiveHuffman => {
+            // single pass: no statistics and no dictionary are needed
+            let mut coder = AdaptiveHuffman::new();
+
+            write_compressed_file(
+                &mut file_reader,
+                &mut output_file,
+                &archive_header,
+                |buf, _, bits| {
+                    for byte in buf {
+                        coder.encode(*byte, bits);
+                    }
+                },
+            )?
+        }
+        Coding::Arithmetic | Coding::AdaptiveArithmetic => {
+            let mut model = match settings.coding {
+                Coding::Arithmetic => {
+                    let model = FrequencyModel::from_stats(&parse_file(&mut file_reader));
+                    create_frequency_header(&mut output_file, model.frequencies())?;
+                    model
+                }
+                _ => FrequencyModel::adaptive(),
+            };
+            let mut coder = ArithmeticEncoder::new();
+
+            write_compressed_file(
+                &mut file_reader,
+                &mut output_file,
+                &archive_header,
+                |buf, end_of_file, bits| {
+                    for byte in buf {
+                        coder.encode_symbol(&mut model, *byte, bits);
+                    }
+                    if end_of_file {
+                        coder.finish(bits);
+                    }
+                },
+            )?
+        }
+        Coding::Lzss { window_bits } => {
+            let window_bits = window_bits.unwrap_or(lzss::DEFAULT_WINDOW_BITS);
+            let codes = build_lzss_codes(&mut file_reader, window_bits)?;
+
+            output_file.write_all(&[window_bits])?;
+            create_dictionary_header(&mut output_file, &codes.literals)?;
+            create_dictionary_header(&mut output_file, &codes.lengths)?;
+            create_dictionary_header(&mut output_file, &codes.distance_slots)?;
+
+            let mut encoder = LzssEncoder::new(window_bits)?;
+            write_compressed_file(
+                &mut file_reader,
+                &mut output_file,
+                &archive_header,
+                |buf, end_of_file, bits| {
+                    encoder.encode(buf, end_of_file, |token| codes.write_token(token, bits));
+                },
+            )?
+        }
+        Coding::ContextHuffman => {
+            let mut stats = ContextStats::new();
+            file_reader.rewind();
+            file_reader.read_file_in_chunks(|buf, _| {
+                stats.update(buf);
+                Ok(())
+            })?;
+
+            let codes = ContextCodes::build(&stats);
+            println!(
+                "{} of 256 contexts have own code tables",
+                codes.own.iter().filter(|codes| codes.is_some()).count()
+            );
+            create_context_header(&mut output_file, &codes)?;
+
+            let mut previous = context::INITIAL_CONTEXT;
+            write_compressed_file(
+                &mut file_reader,
+                &mut output_file,
+                &archive_header,
+                |buf, _, bits| {
+                    for byte in buf {
+                        bits.add_bit_sequence(codes.code(previous, *byte));
+                        previous = *byte;
+                    }
+                },
+            )?
+        }
+        Coding::Bwt => {
+            let codes = build_bwt_codes(&mut file_reader)?;
+            create_dictionary_header(&mut output_file, &codes.values)?;
+            create_dictionary_header(&mut output_file, &codes.runs)?;
+
+            let mut blocks = Blocks::new();
+            write_compressed_file(
+                &mut file_reader,
+                &mut output_file,
+                &archive_header,
+                |buf, end_of_file, bits| {
+                    blocks.push(buf, end_of_file, |block| {
+                        let (primary, symbols) = bwt::encode_block(block);
+
+                        bits.add_value(primary as u64, 32);
+                        for symbol in symbols {
+                            codes.write_symbol(symbol, bits);
+                        }
+                    });
+                },
+            )?
+        }
+        Coding::Lzw { max_code_bits } => {
+            let max_bits = max_code_bits.unwrap_or(lzw::DEFAULT_CODE_BITS);
+            let mut coder = LzwEncoder::new(max_bits)?;
+            output_file.write_all(&[max_bits])?;
+
+            write_compressed_file(
+                &mut file_reader,
+                &mut output_file,
+                &archive_header,
+                |buf, end_of_file, bits| {
+                    coder.encode(buf, bits);
+                    if end_of_file {
+                        coder.finish(bits);
+                    }
+                },
+            )?
+        }
+        Coding::Ppm { order } => {
+            let order = order.unwrap_or(ppm::DEFAULT_ORDER);
+            let mut model = PpmModel::new(order)?;
+            output_file.write_all(&[order])?;
+            let mut coder = ArithmeticEncoder::new();
+
+            write_compressed_file(
+                &mut file_reader,
+                &mut output_file,
+                &archive_header,
+                |buf, end_of_file, bits| {
+                    for byte in buf {
+                        model.encode(*byte, &mut coder, bits);
+                    }
+                    if end_of_file {
+                        coder.finish(bits);
+                    }
+                },
+            )?
+        }
+        Coding::Ans => {
+            let table = AnsTable::from_stats(&parse_file(&mut file_reader));
+            create_frequency_header(&mut output_file, table.frequencies())?;
+            let mut coder = AnsEncoder::new(table);
+
+            write_compressed_file(
+                &mut file_reader,
+                &mut output_file,
+                &archive_header,
+                |buf, end_of_file, bits| {
+                    coder.encode(buf, bits);
+                    if end_of_file {
+                        coder.finish(bits);
+                    }
+                },
+            )?
+        }
+    };
 
-    file_reader.rewind();
-    if let Err(err) = write_compressed_file(
-        &mut file_reader,
-        &mut output_file,
-        &dictionary,
-        settings.hamming_code_length,
-    ) {
-        return Err(err);
+    output_file.write_all(&checksum.to_le_bytes())?;
+
+    if original_length != 0 {
+        println!(
+            "Archive takes {:.3} bits per symbol of original file",
+            output_file.metadata()?.len() as f64 * 8.0 / original_length as f64
+        );
     }
 
     println!("Compressing completed succesfully!");
     Ok(())
 }
 
-fn create_dictionary_header(
-    file: &mut File,
-    dict: &HashMap<u8, Vec<u8>>,
-    hamming_code_length: Option<u8>,
-) -> Result<(), Error> {
-    let write_error = "Could not parse directory into file";
-    let mut bitmap = BitMap::new();
+/// gathers statistics of the file and builds prefix code of chosen type, canonical unless it is alphabetic
+fn build_dictionary(
+    file_reader: &mut FileReader,
+    coding: Coding,
+) -> Result<HashMap<u8, Vec<u8>>, Error> {
+    let stats = parse_file(file_reader);
+    let (quantities, total) = stats;
+
+    let max_code_length = match coding {
+        Coding::Huffman { max_code_length } => max_code_length,
+        _ => None,
+    };
+    let dictionary = match coding {
+        Coding::HuTucker => hu_tucker::encode(stats),
+        Coding::Huffman {
+            max_code_length: None,
+        } => canonical::canonize(&huffman::encode(stats)),
+        Coding::Huffman {
+            max_code_length: Some(max_length),
+        } => huffman::encode_limited(stats, max_length as usize)
+            .map_err(|err| Error::new(ErrorKind::InvalidInput, err))?,
+        Coding::Shannon => canonical::canonize(&shannon::encode(stats)),
+        Coding::ShannonFanoElias => canonical::canonize(&shannon_fano_elias::encode(stats)),
+        _ => canonical::canonize(&shannon_fano::encode(stats)),
+    };
 
-    if let Some(code_length) = hamming_code_length {
-        file.write_all(&HAMMING_CODE_LENGTH_KEY)?;
-        file.write_all(&[code_length])?;
+    if total != 0 {
+        let code_length: u64 = dictionary
+            .iter()
+            .map(|(symbol, code)| quantities[*symbol as usize] * code.len() as u64)
+            .sum();
+        let entropy = calculate_entropy(calculate_information_amount(&quantities, total), total);
+        println!(
+            "Average code length is {:.3} bits (entropy is {:.3} bits)",
+            code_length as f64 / total as f64,
+            entropy
+        );
     }
 
-    for key in dict.keys() {
-        // write original symbol
-        file.write(&[key.clone()])?;
+    let longest_code = dictionary
+        .values()
+        .map(|code| code.len())
+        .max()
+        .unwrap_or(0);
+    match max_code_length {
+        Some(max_length) => println!(
+            "Longest code is {} bits (limit is {} bits)",
+            longest_code, max_length
+        ),
+        None => println!("Longest code is {} bits", longest_code),
+    }
 
-        let bit_sequence = dict.get(key).unwrap();
+    Ok(dictionary)
+}
 
-        // write code length
-        file.write(&[bit_sequence.len() as u8])?;
+/// gathers statistics of the file and builds canonical D-ary Huffman code, reports its efficiency
+fn build_d_ary_dictionary(file_reader: &mut FileReader, arity: u8) -> HashMap<u8, Vec<u8>> {
+    let stats = parse_file(file_reader);
+    let (quantities, total) = stats;
+
+    let lengths = huffman::encode_d_ary(stats, arity)
+        .iter()
+        .map(|(symbol, code)| (*symbol, code.len()))
+        .collect();
+    let dictionary = canonical::assign_d_ary_codes(&lengths, arity);
+
+    if total != 0 {
+        let code_length: u64 = dictionary
+            .iter()
+            .map(|(symbol, code)| quantities[*symbol as usize] * code.len() as u64)
+            .sum();
+        let average = code_length as f64 / total as f64;
+        // entropy in base D is the binary one divided by log2 D
+        let entropy = calculate_entropy(calculate_information_amount(&quantities, total), total)
+            / (arity as f64).log2();
+        println!(
+            "Average code length is {:.3} digits of base {} (entropy is {:.3} digits), efficiency is {:.2}%",
+            average,
+            arity,
+            entropy,
+            100.0 * entropy / average
+        );
+
+        let (group_digits, group_bits) = huffman::digit_packing(arity);
+        println!(
+            "Every {} digits are packed into {} bits",
+            group_digits, group_bits
+        );
+    }
+
+    dictionary
+}
 
-        // get symbol's code and write it to file
-        bitmap.add_bit_sequence(bit_sequence);
+/// gathers statistics of n-grams and builds canonical Huffman code of them, compares it with
+/// code of single bytes
+fn build_ngram_dictionary(file_reader: &mut FileReader, length: u8) -> HashMap<u32, Vec<u8>> {
+    let (quantities, count) = parse_file_ngrams(file_reader, length);
+
+    // sorted, so equal quantities are merged in the same order on every run
+    let mut ngrams: Vec<u32> = quantities.keys().copied().collect();
+    ngrams.sort();
+    let ngram_quantities: Vec<u64> = ngrams.iter().map(|ngram| quantities[ngram]).collect();
+    let lengths: HashMap<u32, usize> = ngrams
+        .into_iter()
+        .zip(huffman::code_lengths(&ngram_quantities))
+        .collect();
+    let dictionary = canonical::assign_codes(&lengths);
+
+    let (byte_quantities, size) = parse_file(file_reader);
+    if size != 0 {
+        let tail_bits = 8 * (size - count * length as u64);
+        let ngram_bits: u64 = dictionary
+            .iter()
+            .map(|(ngram, code)| quantities[ngram] * code.len() as u64)
+            .sum();
+        let byte_bits: u64 = huffman::encode((byte_quantities, size))
+            .iter()
+            .map(|(symbol, code)| byte_quantities[*symbol as usize] * code.len() as u64)
+            .sum();
+        let entropy = calculate_entropy(calculate_information_amount(&byte_quantities, size), size);
+
+        println!(
+            "Code of {}-grams takes {:.3} bits per byte, code of single bytes takes {:.3} bits (entropy is {:.3} bits)",
+            length,
+            (ngram_bits + tail_bits) as f64 / size as f64,
+            byte_bits as f64 / size as f64,
+            entropy
+        );
+    }
+
+    dictionary
+}
 
-        if let Err(_) = bitmap.flush_to_file(file) {
-            return Err(Error::new(ErrorKind::Other, write_error));
-        };
+fn create_context_header(file: &mut File, codes: &ContextCodes) -> Result<(), Error> {
+    let mut present = [0_u8; 32];
+    for (context, own) in codes.own.iter().enumerate() {
+        if own.is_some() {
+            present[context / 8] |= 1 << (context % 8);
+        }
     }
+    file.write_all(&present)?;
 
-    // mark delimiter that corresponds to the end of the file
-    file.write(&DICTIONARY_END)?;
+    create_dictionary_header(file, &codes.shared)?;
+    for own in codes.own.iter().flatten() {
+        create_dictionary_header(file, own)?;
+    }
 
     Ok(())
 }
 
-fn write_compressed_file(
-    file_reader: &mut FileReader,
-    output_file: &mut File,
-    dictionary: &HashMap<u8, Vec<u8>>,
-    hamming_code_length: Option<u8>,
-) -> Result<(), Error> {
-    let mut bitmap = BitMap::new();
-
-    if let Some(hmcl) = hamming_code_length {
-        let data_len = hamming::data_length(hmcl as usize);
-
-        file_reader.read_file_in_chunks(|buf, end_of_file| {
-            transform_data_to_hamming_codes(
-                output_file,
-                dictionary,
-                &mut bitmap,
-                buf,
-                end_of_file,
-                data_len,
-            )
-        })?;
-    } else {
-        file_reader.read_file_in_chunks(|buf, end_of_file| {
-            transofrm_data_to_raw(output_file, dictionary, &mut bitmap, buf, end_of_file)
-        })?;
-    };
+/// Huffman codes of move-to-front values and of lengths of zero runs
+struct BwtCodes {
+    values: HashMap<u8, Vec<u8>>,
+    runs: HashMap<u8, Vec<u8>>,
+}
 
-    Ok(())
+impl BwtCodes {
+    fn write_symbol(&self, symbol: Symbol, bits: &mut BitMap) {
+        match symbol {
+            Symbol::Value(value) => bits.add_bit_sequence(&self.values[&value]),
+            Symbol::ZeroRun(length) => {
+                bits.add_bit_sequence(&self.values[&0]);
+                bits.add_bit_sequence(&self.runs[&((length - 1) as u8)]);
+            }
+        }
+    }
 }
 
-fn transform_data_to_hamming_codes(
-    output_file: &mut File,
-    dictionary: &HashMap<u8, Vec<u8>>,
-    bitmap: &mut BitMap,
-    buf: &[u8],
-    end_of_file: bool,
-    data_len: usize,
-) -> Result<(), Error> {
-    let chunk_size = 1024;
-    let buf_len = buf.len();
+/// transforms all blocks of the file to build Huffman codes from their statistics; blocks are
+/// transformed once again while writing, so that whole file is never kept in memory
+fn build_bwt_codes(file_reader: &mut FileReader) -> Result<BwtCodes, Error> {
+    let mut values = [0_u64; 256];
+    let mut runs = [0_u64; 256];
 
-    let mut data_container = BitMap::new();
+    let mut blocks = Blocks::new();
+    file_reader.rewind();
+    file_reader.read_file_in_chunks(|buf, end_of_file| {
+        blocks.push(buf, end_of_file, |block| {
+            for symbol in bwt::encode_block(block).1 {
+                match symbol {
+                    Symbol::Value(value) => values[value as usize] += 1,
+                    Symbol::ZeroRun(length) => {
+                        values[0] += 1;
+                        runs[length - 1] += 1;
+                    }
+                }
+            }
+        });
+        Ok(())
+    })?;
 
-    for chunk_pos in (0..buf_len).step_by(chunk_size) {
-        let valid_bytes_chunk = &buf[chunk_pos..min(chunk_pos + chunk_size, buf_len)];
+    let code = |quantities: [u64; 256]| {
+        canonical::canonize(&huffman::encode((quantities, quantities.iter().sum())))
+    };
+    Ok(BwtCodes {
+        values: code(values),
+        runs: code(runs),
+    })
+}
+
+/// Huffman codes of the three alphabets of LZSS tokens
+struct LzssCodes {
+    literals: HashMap<u8, Vec<u8>>,
+    lengths: HashMap<u8, Vec<u8>>,
+    distance_slots: HashMap<u8, Vec<u8>>,
+}
+
+impl LzssCodes {
+    fn write_token(&self, token: Token, bits: &mut BitMap) {
+        match token {
+            Token::Literal(byte) => {
+                bits.add_bit(0);
+                bits.add_bit_sequence(&self.literals[&byte]);
+            }
+            Token::Match { length, distance } => {
+                let (slot, extra_bits, extra) = lzss::distance_slot(distance);
 
-        for byte in valid_bytes_chunk {
-            if dictionary.contains_key(byte) {
-                data_container.add_bit_sequence(dictionary.get(byte).unwrap());
+                bits.add_bit(1);
+                bits.add_bit_sequence(&self.lengths[&((length - MIN_MATCH) as u8)]);
+                bits.add_bit_sequence(&self.distance_slots[&slot]);
+                bits.add_value(extra as u64, extra_bits);
             }
         }
     }
+}
 
-    let data_bits = data_container.get_all_bits();
+/// splits the file into LZSS tokens and builds Huffman codes from their statistics
+fn build_lzss_codes(file_reader: &mut FileReader, window_bits: u8) -> Result<LzssCodes, Error> {
+    let mut literals = [0_u64; 256];
+    let mut lengths = [0_u64; 256];
+    let mut distance_slots = [0_u64; 256];
 
-    bitmap.add_bit_sequence(&hamming::add_parity_package(&data_bits, data_len));
+    let mut encoder = LzssEncoder::new(window_bits)?;
+    file_reader.rewind();
+    file_reader.read_file_in_chunks(|buf, end_of_file| {
+        encoder.encode(buf, end_of_file, |token| match token {
+            Token::Literal(byte) => literals[byte as usize] += 1,
+            Token::Match { length, distance } => {
+                lengths[length - MIN_MATCH] += 1;
+                distance_slots[lzss::distance_slot(distance).0 as usize] += 1;
+            }
+        });
+        Ok(())
+    })?;
 
-    if let Err(_) = if end_of_file {
-        bitmap.flush_to_file(output_file)
-    } else {
-        bitmap.flush_filled_to_file(output_file)
-    } {
-        return Err(Error::new(
-            ErrorKind::BrokenPipe,
-            "Error while writing to file",
-        ));
+    let code = |quantities: [u64; 256]| {
+        canonical::canonize(&huffman::encode((quantities, quantities.iter().sum())))
     };
+    Ok(LzssCodes {
+        literals: code(literals),
+        lengths: code(lengths),
+        distance_slots: code(distance_slots),
+    })
+}
 
-    Ok(())
+fn create_dictionary_header(file: &mut File, dict: &HashMap<u8, Vec<u8>>) -> Result<(), Error> {
+    let lengths = dict.iter().map(|(s, code)| (*s, code.len())).collect();
+    write_length_counts(file, &canonical::length_counts(&lengths))?;
+
+    let symbols: Vec<u8> = canonical::canonical_order(&lengths)
+        .iter()
+        .map(|(symbol, _)| *symbol)
+        .collect();
+    file.write_all(&symbols)
 }
 
-fn transofrm_data_to_raw(
-    output_file: &mut File,
-    dictionary: &HashMap<u8, Vec<u8>>,
-    bitmap: &mut BitMap,
-    buf: &[u8],
-    end_of_file: bool,
+fn create_ngram_header(
+    file: &mut File,
+    length: u8,
+    dict: &HashMap<u32, Vec<u8>>,
 ) -> Result<(), Error> {
-    for byte in buf {
-        if dictionary.contains_key(byte) {
-            bitmap.add_bit_sequence(dictionary.get(byte).unwrap());
-        }
+    file.write_all(&[length])?;
+
+    let lengths = dict.iter().map(|(s, code)| (*s, code.len())).collect();
+    write_length_counts(file, &canonical::length_counts(&lengths))?;
+
+    let mut symbols = vec![];
+    for (ngram, _) in canonical::canonical_order(&lengths) {
+        symbols.extend(ngram::split(ngram, length));
     }
+    file.write_all(&symbols)
+}
 
-    if let Err(_) = if end_of_file {
-        bitmap.flush_to_file(output_file)
-    } else {
-        bitmap.flush_filled_to_file(output_file)
-    } {
+fn create_words_header(file: &mut File, code: &WordCode) -> Result<(), Error> {
+    write_length_counts(file, &code.length_counts())?;
+
+    let mut tokens = vec![];
+    for token in &code.tokens {
+        tokens.push(token.len() as u8);
+        tokens.extend_from_slice(token);
+    }
+    file.write_all(&tokens)
+}
+
+/// writes "Max length" and "Counts" of a dictionary
+fn write_length_counts(file: &mut File, counts: &[usize]) -> Result<(), Error> {
+    if counts.len() > u8::MAX as usize {
         return Err(Error::new(
-            ErrorKind::BrokenPipe,
-            "Error while writing to file",
+            ErrorKind::InvalidInput,
+            format!(
+                "Code length {} does not fit into dictionary header",
+                counts.len()
+            ),
         ));
-    };
+    }
 
-    Ok(())
+    let mut header = BitMap::new();
+    universal::encode_exp_golomb(counts.len() as u64, 0, &mut header);
+    for count in counts {
+        universal::encode_exp_golomb(*count as u64, 0, &mut header);
+    }
+    header
+        .flush_to_file(file)
+        .map_err(|_| Error::new(ErrorKind::BrokenPipe, "Error while writing to file"))
+}
+
+fn create_frequency_header(file: &mut File, frequencies: &[u32; 256]) -> Result<(), Error> {
+    let present: Vec<(usize, &u32)> = frequencies
+        .iter()
+        .enumerate()
+        .filter(|(_, frequency)| **frequency != 0)
+        .collect();
+
+    let mut header = (present.len() as u16).to_le_bytes().to_vec();
+    for (symbol, frequency) in present {
+        header.push(symbol as u8);
+        header.extend_from_slice(&(*frequency as u16).to_le_bytes());
+    }
+
+    file.write_all(&header)
+}
+
+/// returns frequencies of all symbols and offset of the first data byte
+fn read_frequency_header(file: &File, start: usize) -> Result<([u32; 256], usize), Error> {
+    let mut offset = start as u64;
+
+    let mut count = [0_u8; 2];
+    file.read_exact_at(&mut count, offset)?;
+    offset += 2;
+
+    let mut entries = vec![0_u8; 3 * u16::from_le_bytes(count) as usize];
+    file.read_exact_at(&mut entries, offset)?;
+    offset += entries.len() as u64;
+
+    let mut frequencies = [0; 256];
+    for entry in entries.chunks(3) {
+        frequencies[entry[0] as usize] = u16::from_le_bytes([entry[1], entry[2]]) as u32;
+    }
+
+    Ok((frequencies, offset as usize))
+}
+
+/// passes every chunk of original file to `encode_chunk`, which appends encoded bits to bitmap;
+/// the flag tells that chunk is the last one. Returns CRC-32 of original file
+fn write_compressed_file<F>(
+    file_reader: &mut FileReader,
+    output_file: &mut File,
+    archive_header: &ArchiveHeader,
+    mut encode_chunk: F,
+) -> Result<u32, Error>
+where
+    F: FnMut(&[u8], bool, &mut BitMap),
+{
+    let mut crc = Crc32::new();
+    let mut channel = ChannelWriter::new(output_file, archive_header)?;
+
+    file_reader.rewind();
+    file_reader.read_file_in_chunks(|buf, end_of_file| {
+        crc.update(buf);
+        encode_chunk(buf, end_of_file, channel.data());
+        channel.flush(end_of_file)
+    })?;
+
+    Ok(crc.finalize())
 }
 
 pub fn decode_file(file_info: Option<FileInfo>) -> Result<(), Error> {
@@ -247,152 +888,574 @@ pub fn decode_file(file_info: Option<FileInfo>) -> Result<(), Error> {
         Err(_) => return Err(Error::new(ErrorKind::Other, "Could not create output file")),
     };
 
-    let mut dictionary: HashMap<Vec<u8>, u8> = HashMap::new();
-
     // actual algorithm of decoding starts here
-    let (header_offset, hamming_code_len) = read_dictionary_header(&encoded_file, &mut dictionary);
+    let (archive_header, section_offset) = ArchiveHeader::read_from(&encoded_file)?;
+
+    let stored_checksum = if archive_header.has_checksum {
+        let trailer_offset = encoded_file
+            .metadata()?
+            .len()
+            .checked_sub(CHECKSUM_LENGTH as u64)
+            .filter(|offset| *offset >= section_offset as u64)
+            .ok_or_else(|| {
+                Error::new(ErrorKind::UnexpectedEof, "Archive has no checksum trailer")
+            })?;
+
+        let mut trailer = [0_u8; CHECKSUM_LENGTH];
+        encoded_file.read_exact_at(&mut trailer, trailer_offset)?;
+        Some((u32::from_le_bytes(trailer), trailer_offset))
+    } else {
+        None
+    };
 
-    let mut file_reader = FileReader::new(encoded_file);
-    let trie = build_codes_trie(&dictionary);
-    file_reader.set_offset(header_offset);
+    let data_end = stored_checksum.map(|(_, trailer_offset)| trailer_offset as usize);
+    let parity_report = Rc::new(RefCell::new(ParityReport::default()));
+    let open_data = |data_offset| {
+        open_data_section(
+            &encoded_file,
+            &archive_header,
+            data_offset,
+            data_end,
+            parity_report.clone(),
+        )
+    };
+    // decoder of a "Dictionary" section and offset of the byte after it
+    let read_code = |start| -> Result<(CanonicalDecoder, usize), Error> {
+        let mut code_lengths = HashMap::new();
+        let end = read_dictionary_header(&encoded_file, start, 2, &mut code_lengths)?;
+        Ok((CanonicalDecoder::new(&code_lengths), end))
+    };
 
-    match write_decoded_file(
-        &mut file_reader,
-        &mut decoded_file,
-        &trie,
-        &dictionary,
-        hamming_code_len,
-    ) {
-        Ok(_) => {
-            println!("Decompressing completed successfully!");
-            Ok(())
+    let original_length = archive_header.original_length;
+    let checksum = match archive_header.code_type {
+        CodeType::ShannonFano
+        | CodeType::Huffman
+        | CodeType::Shannon
+        | CodeType::ShannonFanoElias => {
+            let mut code_lengths: HashMap<u8, usize> = HashMap::new();
+            let data_offset =
+                read_dictionary_header(&encoded_file, section_offset, 2, &mut code_lengths)?;
+
+            let mut bits = open_data(data_offset)?;
+            let mut decoder = CanonicalDecoder::new(&code_lengths);
+            write_decoded_file(&mut decoded_file, original_length, |decoded| {
+                decoded.push(decoder.decode(&mut bits)?);
+                Ok(())
+            })?
         }
-        Err(err) => Err(err),
-    }
-}
+        CodeType::DaryHuffman => {
+            let mut arity = [0_u8];
+            encoded_file.read_exact_at(&mut arity, section_offset as u64)?;
+            let mut reader = DigitReader::new(arity[0])
+                .map_err(|err| Error::new(ErrorKind::InvalidData, err.to_string()))?;
+
+            let mut code_lengths: HashMap<u8, usize> = HashMap::new();
+            let data_offset = read_dictionary_header(
+                &encoded_file,
+                section_offset + 1,
+                arity[0],
+                &mut code_lengths,
+            )?;
+
+            let mut bits = open_data(data_offset)?;
+            let mut decoder = CanonicalDecoder::with_arity(&code_lengths, arity[0]);
+            write_decoded_file(&mut decoded_file, original_length, |decoded| loop {
+                if let Some(symbol) = decoder.push_digit(reader.read_digit(&mut bits)?)? {
+                    decoded.push(symbol);
+                    return Ok(());
+                }
+            })?
+        }
+        CodeType::HuTucker => {
+            let mut code_lengths: HashMap<u8, usize> = HashMap::new();
+            let data_offset =
+                read_dictionary_header(&encoded_file, section_offset, 2, &mut code_lengths)?;
+
+            let mut bits = open_data(data_offset)?;
+            let decoder = AlphabeticDecoder::new(&code_lengths)?;
+            write_decoded_file(&mut decoded_file, original_length, |decoded| {
+                decoded.push(decoder.decode(&mut bits)?);
+                Ok(())
+            })?
+        }
+        CodeType::Tunstall => {
+            let mut code_bits = [0_u8];
+            encoded_file.read_exact_at(&mut code_bits, section_offset as u64)?;
+            let (frequencies, data_offset) =
+                read_frequency_header(&encoded_file, section_offset + 1)?;
+            let code = TunstallCode::new(&frequencies, code_bits[0])
+                .map_err(|err| Error::new(ErrorKind::InvalidData, err.to_string()))?;
+
+            let mut bits = open_data(data_offset)?;
+            write_decoded_file(&mut decoded_file, original_length, |decoded| {
+                code.decode(&mut bits, decoded)
+            })?
+        }
+        CodeType::NgramHuffman => {
+            let (length, code_lengths, data_offset) =
+                read_ngram_header(&encoded_file, section_offset)?;
+
+            let mut bits = open_data(data_offset)?;
+            let mut decoder = CanonicalDecoder::new(&code_lengths);
+            let mut remaining_ngrams = original_length / length as u64;
+            write_decoded_file(&mut decoded_file, original_length, |decoded| {
+                if remaining_ngrams == 0 {
+                    decoded.push(bits.read_bits(8)? as u8);
+                    return Ok(());
+                }
 
-fn read_dictionary_header(file: &File, dict: &mut HashMap<Vec<u8>, u8>) -> (usize, Option<usize>) {
-    let mut buf = [0_u8; 2048]; // 2Kb buffer
-    let mut offset = 0;
-    file.read_at(&mut buf, 0).unwrap();
+                decoded.extend(ngram::split(decoder.decode(&mut bits)?, length));
+                remaining_ngrams -= 1;
+                Ok(())
+            })?
+        }
+        CodeType::WordHuffman => {
+            let (tokens, counts, data_offset) = read_words_header(&encoded_file, section_offset)?;
+
+            let mut bits = open_data(data_offset)?;
+            let mut decoder = WordDecoder::new(tokens, &counts);
+            write_decoded_file(&mut decoded_file, original_length, |decoded| {
+                decoder.decode(&mut bits, decoded)
+            })?
+        }
+        CodeType::AdaptiveHuffman => {
+            let mut bits = open_data(section_offset)?;
+            let mut decoder = AdaptiveHuffman::new();
+            write_decoded_file(&mut decoded_file, original_length, |decoded| {
+                decoded.push(decoder.decode(&mut bits)?);
+                Ok(())
+            })?
+        }
+        CodeType::Arithmetic | CodeType::AdaptiveArithmetic => {
+            let (mut model, data_offset) = match archive_header.code_type {
+                CodeType::Arithmetic => {
+                    let (frequencies, data_offset) =
+                        read_frequency_header(&encoded_file, section_offset)?;
+
+                    let total: u64 = frequencies.iter().map(|f| *f as u64).sum();
+                    if total > arithmetic::MAX_TOTAL as u64 {
+                        return Err(Error::new(
+                            ErrorKind::InvalidData,
+                            "Frequencies in archive header exceed maximal total",
+                        ));
+                    }
+                    if total == 0 && original_length != 0 {
+                        return Err(Error::new(
+                            ErrorKind::InvalidData,
+                            "Frequencies in archive header are all zero",
+                        ));
+                    }
+                    (FrequencyModel::from_frequencies(frequencies), data_offset)
+                }
+                _ => (FrequencyModel::adaptive(), section_offset),
+            };
 
-    let mut bitmap = BitMap::new();
+            let mut bits = open_data(data_offset)?;
+            let mut decoder = ArithmeticDecoder::new(&mut bits)?;
+            write_decoded_file(&mut decoded_file, original_length, |decoded| {
+                decoded.push(decoder.decode_symbol(&mut model, &mut bits)?);
+                Ok(())
+            })?
+        }
+        CodeType::Lzss => {
+            let mut window_bits = [0_u8];
+            encoded_file.read_exact_at(&mut window_bits, section_offset as u64)?;
+            let mut decoder = LzssDecoder::new(window_bits[0])
+                .map_err(|err| Error::new(ErrorKind::InvalidData, err.to_string()))?;
+
+            let (mut literals, offset) = read_code(section_offset + 1)?;
+            let (mut lengths, offset) = read_code(offset)?;
+            let (mut distance_slots, data_offset) = read_code(offset)?;
+
+            let mut bits = open_data(data_offset)?;
+            write_decoded_file(&mut decoded_file, original_length, |decoded| {
+                let token = if bits.read_bit()? == 0 {
+                    Token::Literal(literals.decode(&mut bits)?)
+                } else {
+                    let length = lengths.decode(&mut bits)? as usize + MIN_MATCH;
+                    let slot = distance_slots.decode(&mut bits)?;
+                    if slot >= 2 * lzss::MAX_WINDOW_BITS {
+                        return Err(Error::new(
+                            ErrorKind::InvalidData,
+                            format!("Unknown distance slot {}", slot),
+                        ));
+                    }
+
+                    let extra = bits.read_bits(lzss::extra_bits(slot))? as usize;
+                    Token::Match {
+                        length,
+                        distance: lzss::distance_from_slot(slot, extra),
+                    }
+                };
+
+                decoder.decode(token, decoded)
+            })?
+        }
+        CodeType::ContextHuffman => {
+            let mut present = [0_u8; 32];
+            encoded_file.read_exact_at(&mut present, section_offset as u64)?;
+
+            let mut shared = HashMap::new();
+            let mut offset =
+                read_dictionary_header(&encoded_file, section_offset + 32, 2, &mut shared)?;
+            let mut own = vec![None; 256];
+            for (context, lengths) in own.iter_mut().enumerate() {
+                if (present[context / 8] >> (context % 8)) & 1 == 1 {
+                    let mut own_lengths = HashMap::new();
+                    offset = read_dictionary_header(&encoded_file, offset, 2, &mut own_lengths)?;
+                    *lengths = Some(own_lengths);
+                }
+            }
 
-    let hamming_code_len = if &buf[..HAMMING_CODE_LENGTH_KEY.len()] == HAMMING_CODE_LENGTH_KEY {
-        offset += HAMMING_CODE_LENGTH_KEY.len() + 1;
-        Some(buf[HAMMING_CODE_LENGTH_KEY.len()] as usize)
-    } else {
-        None
+            let mut bits = open_data(offset)?;
+            let mut decoder = ContextDecoder::new(&shared, &own);
+            write_decoded_file(&mut decoded_file, original_length, |decoded| {
+                decoded.push(decoder.decode(&mut bits)?);
+                Ok(())
+            })?
+        }
+        CodeType::Bwt => {
+            let (mut values, offset) = read_code(section_offset)?;
+            let (mut runs, data_offset) = read_code(offset)?;
+
+            let mut bits = open_data(data_offset)?;
+            let mut remaining = original_length;
+            let mut block_values = Vec::with_capacity(bwt::BLOCK_SIZE);
+            write_decoded_file(&mut decoded_file, original_length, |decoded| {
+                let block_length = remaining.min(bwt::BLOCK_SIZE as u64) as usize;
+                let primary = bits.read_bits(32)? as usize;
+
+                block_values.clear();
+                while block_values.len() < block_length {
+                    match values.decode(&mut bits)? {
+                        0 => {
+                            let run = runs.decode(&mut bits)? as usize + 1;
+                            block_values.resize(block_values.len() + run, 0);
+                        }
+                        value => block_values.push(value),
+                    }
+                }
+                if block_values.len() > block_length {
+                    return Err(Error::new(
+                        ErrorKind::InvalidData,
+                        "Run of zeros crosses the end of BWT block",
+                    ));
+                }
+
+                decoded.extend(bwt::decode_block(&block_values, primary)?);
+                remaining -= block_length as u64;
+                Ok(())
+            })?
+        }
+        CodeType::Lzw => {
+            let mut max_bits = [0_u8];
+            encoded_file.read_exact_at(&mut max_bits, section_offset as u64)?;
+            let mut decoder = LzwDecoder::new(max_bits[0])
+                .map_err(|err| Error::new(ErrorKind::InvalidData, err.to_string()))?;
+
+            let mut bits = open_data(section_offset + 1)?;
+            write_decoded_file(&mut decoded_file, original_length, |decoded| {
+                decoder.decode(&mut bits, decoded)
+            })?
+        }
+        CodeType::Ppm => {
+            let mut order = [0_u8];
+            encoded_file.read_exact_at(&mut order, section_offset as u64)?;
+            let mut model = PpmModel::new(order[0])
+                .map_err(|err| Error::new(ErrorKind::InvalidData, err.to_string()))?;
+
+            let mut bits = open_data(section_offset + 1)?;
+            let mut decoder = ArithmeticDecoder::new(&mut bits)?;
+            write_decoded_file(&mut decoded_file, original_length, |decoded| {
+                decoded.push(model.decode(&mut decoder, &mut bits)?);
+                Ok(())
+            })?
+        }
+        CodeType::Ans => {
+            let (frequencies, data_offset) = read_frequency_header(&encoded_file, section_offset)?;
+
+            let mut bits = open_data(data_offset)?;
+            let mut decoder = AnsDecoder::new(
+                AnsTable::from_frequencies(frequencies, original_length)?,
+                original_length,
+            );
+            write_decoded_file(&mut decoded_file, original_length, |decoded| {
+                decoded.push(decoder.decode(&mut bits)?);
+                Ok(())
+            })?
+        }
     };
 
-    // reading dictionary from header of the file
-    while &buf[offset..=offset + 1] != DICTIONARY_END {
-        // get original symbol code
-        let symbol = buf[offset];
-        offset += 1;
+    if archive_header.error_correction != ErrorCorrection::None {
+        print_parity_report(&parity_report.borrow());
+    }
 
-        // get bit sequence length
-        let code_length = buf[offset];
-        offset += 1;
+    if let Some((expected, _)) = stored_checksum {
+        if expected != checksum {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                ChecksumMismatchError {
+                    expected,
+                    actual: checksum,
+                },
+            ));
+        }
+    }
 
-        // get bit sequence
-        let bytes_taken_by_code = code_length.div_ceil(8) as usize;
-        for _ in 0..bytes_taken_by_code {
-            bitmap.add_byte(buf[offset]);
-            offset += 1;
+    println!("Decompressing completed successfully!");
+    Ok(())
+}
+
+fn print_parity_report(report: &ParityReport) {
+    println!(
+        "Error correction checked {} blocks: {} errors corrected, {} blocks have uncorrectable errors",
+        report.blocks_checked,
+        report.corrected.len(),
+        report.uncorrectable.len()
+    );
+    for block in &report.uncorrectable {
+        println!(
+            "Block {}: errors are detected but can not be corrected",
+            block
+        );
+    }
+}
+
+/// decoded data differs from the original one, so archive is damaged
+#[derive(Debug)]
+pub struct ChecksumMismatchError {
+    pub expected: u32,
+    pub actual: u32,
+}
+
+impl Display for ChecksumMismatchError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Archive is corrupted: CRC-32 of decoded data is {:08x}, expected {:08x}",
+            self.actual, self.expected
+        )
+    }
+}
+
+impl std::error::Error for ChecksumMismatchError {}
+
+/// returns offset of the first data byte; `arity` is the amount of digits of the code
+fn read_dictionary_header(
+    file: &File,
+    start: usize,
+    arity: u8,
+    lengths: &mut HashMap<u8, usize>,
+) -> Result<usize, Error> {
+    let (counts, mut offset) = read_length_counts(file, start, 256, arity)?;
+
+    let mut symbols = vec![0_u8; counts.iter().sum()];
+    file.read_exact_at(&mut symbols, offset)?;
+    offset += symbols.len() as u64;
+
+    // symbols are sorted by code length, so the first `counts[0]` have length 1 and so on
+    let mut symbols = symbols.into_iter();
+    for (length, count) in counts.iter().enumerate() {
+        for symbol in symbols.by_ref().take(*count) {
+            lengths.insert(symbol, length + 1);
         }
+    }
+
+    Ok(offset as usize)
+}
 
-        dict.insert(bitmap.get_bits(code_length as usize), symbol);
-        bitmap.clear();
+/// returns length of n-grams, code lengths of n-grams and offset of the first data byte
+fn read_ngram_header(file: &File, start: usize) -> Result<(u8, HashMap<u32, usize>, usize), Error> {
+    let mut length = [0_u8];
+    file.read_exact_at(&mut length, start as u64)?;
+    let length = length[0];
+    ngram::check_length(length)
+        .map_err(|err| Error::new(ErrorKind::InvalidData, err.to_string()))?;
+
+    let (counts, mut offset) = read_length_counts(file, start + 1, 1 << (8 * length), 2)?;
+
+    let mut symbols = vec![0_u8; counts.iter().sum::<usize>() * length as usize];
+    file.read_exact_at(&mut symbols, offset)?;
+    offset += symbols.len() as u64;
+
+    let mut lengths = HashMap::new();
+    let mut ngrams = symbols.chunks(length as usize);
+    for (code_length, count) in counts.iter().enumerate() {
+        for ngram in ngrams.by_ref().take(*count) {
+            lengths.insert(ngram::join(ngram), code_length + 1);
+        }
     }
 
-    offset += 2;
-    (offset, hamming_code_len)
+    Ok((length, lengths, offset as usize))
 }
 
-fn build_codes_trie(dict: &HashMap<Vec<u8>, u8>) -> Trie<u8> {
-    let mut builder = trie_rs::TrieBuilder::new();
-    for code in dict.keys() {
-        builder.push(code);
+/// returns tokens in canonical order, amounts of codes of every length and offset of the first data byte
+fn read_words_header(file: &File, start: usize) -> Result<(Vocabulary, Vec<usize>, usize), Error> {
+    // every token takes at least 2 bytes of the file
+    let max_tokens = file.metadata()?.len() as usize / 2;
+    let (counts, mut offset) = read_length_counts(file, start, max_tokens, 2)?;
+
+    let mut tokens = vec![];
+    for _ in 0..counts.iter().sum() {
+        let mut length = [0_u8];
+        file.read_exact_at(&mut length, offset)?;
+
+        let mut token = vec![0_u8; length[0] as usize];
+        file.read_exact_at(&mut token, offset + 1)?;
+        offset += 1 + token.len() as u64;
+        tokens.push(token);
     }
 
-    builder.build()
+    Ok((tokens, counts, offset as usize))
 }
 
-fn write_decoded_file(
-    file_reader: &mut FileReader,
-    decoded_file: &mut File,
-    trie: &Trie<u8>,
-    dictionary: &HashMap<Vec<u8>, u8>,
-    hamming_code_length: Option<usize>,
-) -> Result<(), Error> {
-    let write_error = "Could not parse directory into file";
+/// reads "Max length" and "Counts" of a dictionary of at most `max_symbols` symbols whose code
+/// has `arity` digits, returns the counts and offset of the first symbol
+fn read_length_counts(
+    file: &File,
+    start: usize,
+    max_symbols: usize,
+    arity: u8,
+) -> Result<(Vec<usize>, u64), Error> {
+    let corrupted = || Error::new(ErrorKind::InvalidData, "Dictionary header is corrupted");
+
+    let mut header = HeaderBits::new(file, start);
+    let max_length = universal::decode_exp_golomb(0, &mut header)?;
+    // canonical decoder keeps codes of the longest length in u128
+    if max_length > u8::MAX as u64 || (arity as u128).checked_pow(max_length as u32).is_none() {
+        return Err(corrupted());
+    }
 
-    let mut bitmap = BitMap::new();
+    let mut counts = vec![];
+    for _ in 0..max_length {
+        let count = universal::decode_exp_golomb(0, &mut header)?;
+        if count > max_symbols as u64 {
+            return Err(corrupted());
+        }
+        counts.push(count as usize);
+    }
+    if counts.iter().sum::<usize>() > max_symbols {
+        return Err(corrupted());
+    }
 
-    let chunk_length = 1024;
-    let mut chunk_start = 0;
+    // Kraft inequality: codes of every length must leave room for one another
+    let mut free_codes: u128 = 1;
+    for count in &counts {
+        free_codes = (free_codes * arity as u128)
+            .checked_sub(*count as u128)
+            .ok_or_else(corrupted)?;
+    }
 
-    let mut decoded_bit_stream = Vec::new();
-    let mut connecting_bits = Vec::new();
+    Ok((counts, header.next_byte()))
+}
 
-    file_reader.read_file_in_chunks(|buf, _| {
-        let buf_len = buf.len();
-        while chunk_start < buf_len {
-            let bytes_chunk = &buf[chunk_start..min(chunk_start + chunk_length, buf_len)];
-            bitmap.add_bytes(bytes_chunk);
+/// reads bits of archive header in the order they are written by `BitMap`
+struct HeaderBits<'a> {
+    file: &'a File,
+    offset: u64, // offset of the next unread byte
+    byte: u8,
+    bit: u8, // the next bit of `byte` to read, 8 when it is used up
+}
 
-            // if hamming codes are used
-            if let Some(msg_len) = hamming_code_length {
-                let mut bits = Vec::from(&connecting_bits[..]);
-                bits.append(&mut bitmap.get_all_bits());
+impl<'a> HeaderBits<'a> {
+    fn new(file: &'a File, start: usize) -> Self {
+        Self {
+            file,
+            offset: start as u64,
+            byte: 0,
+            bit: 8,
+        }
+    }
 
-                let connection_pos = bits.len() - bits.len() % msg_len;
+    /// offset of the byte after the last one that has been read from
+    fn next_byte(&self) -> u64 {
+        self.offset
+    }
+}
 
-                for b in &bits[connection_pos..] {
-                    connecting_bits.push(b.clone());
-                }
+impl BitSource for HeaderBits<'_> {
+    fn read_bit(&mut self) -> Result<u8, Error> {
+        if self.bit == 8 {
+            let mut byte = [0_u8];
+            self.file.read_exact_at(&mut byte, self.offset)?;
+            self.offset += 1;
+            self.byte = byte[0];
+            self.bit = 0;
+        }
 
-                bitmap.clear();
-                bitmap.add_bit_sequence(&mut hamming::remove_parity_package(
-                    &mut bits[..connection_pos],
-                    msg_len,
-                ));
-            }
+        let bit = (self.byte >> self.bit) & 1;
+        self.bit += 1;
+        Ok(bit)
+    }
+}
 
-            decoded_bit_stream.append(&mut bitmap.get_all_bits());
-            bitmap.clear();
+/// reader of archive data that starts at `data_offset` and lasts till `data_end` (or end of file)
+fn open_data_section(
+    file: &File,
+    archive_header: &ArchiveHeader,
+    data_offset: usize,
+    data_end: Option<usize>,
+    parity_report: Rc<RefCell<ParityReport>>,
+) -> Result<ChannelReader, Error> {
+    let mut file_reader = FileReader::new(file.try_clone()?);
+    file_reader.set_offset(data_offset);
+    if let Some(end) = data_end {
+        file_reader.set_end(end);
+    }
 
-            let mut decoded_bytes = Vec::new();
-            let length = decoded_bit_stream.len();
+    ChannelReader::new(file_reader, archive_header, parity_report)
+}
 
-            let mut ptr_lo = 0;
-            let mut ptr_hi = 1;
-            while ptr_hi < length {
-                while !trie.exact_match(&decoded_bit_stream[ptr_lo..ptr_hi]) && ptr_hi < length {
-                    ptr_hi += 1;
-                }
-                if ptr_hi >= length {
-                    break;
-                }
+/// calls `decode_next` until `original_length` bytes are decoded, every call appends at least one byte.
+/// Returns CRC-32 of decoded data
+fn write_decoded_file<F>(
+    decoded_file: &mut File,
+    original_length: u64,
+    mut decode_next: F,
+) -> Result<u32, Error>
+where
+    F: FnMut(&mut Vec<u8>) -> Result<(), Error>,
+{
+    let write_error = "Could not parse directory into file";
+    let chunk_length = 1024;
 
-                decoded_bytes.push(*dictionary.get(&decoded_bit_stream[ptr_lo..ptr_hi]).unwrap());
+    let mut crc = Crc32::new();
+    let mut decoded_count = 0;
+    let mut decoded_bytes = Vec::with_capacity(2 * chunk_length);
+
+    while decoded_count < original_length {
+        if let Err(err) = decode_next(&mut decoded_bytes) {
+            return Err(match err.kind() {
+                ErrorKind::UnexpectedEof => Error::new(
+                    ErrorKind::UnexpectedEof,
+                    format!(
+                        "Archive ended after {} of {} symbols",
+                        decoded_count + decoded_bytes.len() as u64,
+                        original_length
+                    ),
+                ),
+                _ => err,
+            });
+        }
 
-                ptr_lo = ptr_hi;
-                ptr_hi += 1;
-            }
+        let total = decoded_count + decoded_bytes.len() as u64;
+        // everything after the last symbol is padding
+        if total > original_length {
+            decoded_bytes.truncate((original_length - decoded_count) as usize);
+        }
 
-            if let Err(_) = decoded_file.write(decoded_bytes.as_slice()) {
+        if decoded_bytes.len() >= chunk_length || total >= original_length {
+            crc.update(&decoded_bytes);
+            if let Err(_) = decoded_file.write_all(decoded_bytes.as_slice()) {
                 return Err(Error::new(ErrorKind::Other, write_error));
             };
 
-            decoded_bit_stream = Vec::from(&decoded_bit_stream[ptr_lo..]);
-            chunk_start += chunk_length;
+            decoded_count += decoded_bytes.len() as u64;
+            decoded_bytes.clear();
         }
+    }
 
-        chunk_start = 0;
-        Ok(())
-    })
+    Ok(crc.finalize())
 }
 
 fn increment_file_index(filepath: &str) -> String {
@@ -1,16 +1,20 @@
+pub mod channel;
 pub mod constants;
 pub mod file_reader;
 pub mod formulae;
+pub mod header;
 pub mod logic;
 pub mod terminal;
 
 use self::formulae::parse_chunk_for_unique_bytes;
-use crate::types::{FileInfo, FileStats};
+use crate::algorithms::ngram::Ngrams;
+use crate::types::{FileInfo, FileStats, NgramStats};
 use crate::utils::file_reader::FileReader;
 use crate::utils::formulae::{
     calculate_entropy, calculate_information_amount, calculate_max_entropy, calculate_redundancy,
 };
 use crate::utils::terminal::get_line_from_user;
+use std::collections::HashMap;
 use std::env;
 use std::fs::File;
 use std::io::{stdin, stdout, Write};
@@ -33,6 +37,26 @@ pub fn parse_file(file_reader: &mut FileReader) -> FileStats {
     (dictionary, file_size)
 }
 
+/// counts complete n-grams of `length` bytes, bytes of the tail are left out
+pub fn parse_file_ngrams(file_reader: &mut FileReader, length: u8) -> NgramStats {
+    let mut quantities = HashMap::new();
+    let mut count = 0;
+    let mut ngrams = Ngrams::new(length);
+
+    file_reader.rewind();
+    file_reader
+        .read_file_in_chunks(|buf, _| {
+            ngrams.push(buf, |ngram| {
+                *quantities.entry(ngram).or_insert(0) += 1;
+                count += 1;
+            });
+            Ok(())
+        })
+        .unwrap();
+
+    (quantities, count)
+}
+
 pub fn get_stats_and_print(dictionary: &[u64; 256], file_size: u64) {
     let mut distinct_count = 0;
     dictionary.map(|b| {